@@ -0,0 +1,501 @@
+//! Technical indicators computed locally from time series, without calling the API.
+//!
+//! Indicators are computed from the close prices of the entries (and high and low
+//! prices where needed) as `f64`, over the entries of the time series only. Values may
+//! therefore differ from those returned by the API, which computes indicators over the
+//! full history. Each value is dated like the entry it was computed at, and entries
+//! before an indicator has enough data to be computed are skipped.
+use crate::time_series::TimeSeries;
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+/// Represents the value of an indicator at a given date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point<T> {
+    /// Date of the entry the value was computed at.
+    pub date: DateTime<Tz>,
+    /// Value.
+    pub value: T,
+}
+
+/// Represents a value of the moving average convergence/divergence indicator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Macd {
+    /// Difference between the fast and slow exponential moving averages.
+    pub macd: f64,
+    /// Exponential moving average of the MACD.
+    pub signal: f64,
+    /// Difference between the MACD and the signal.
+    pub histogram: f64,
+}
+
+/// Represents a value of the Bollinger Bands indicator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BollingerBands {
+    /// Upper band.
+    pub upper: f64,
+    /// Middle band (simple moving average).
+    pub middle: f64,
+    /// Lower band.
+    pub lower: f64,
+}
+
+/// Represents a value of the stochastic oscillator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stochastic {
+    /// Slow %K.
+    pub k: f64,
+    /// Slow %D.
+    pub d: f64,
+}
+
+/// Simple moving average of the close prices over `period` entries.
+pub fn sma(time_series: &TimeSeries, period: usize) -> Vec<Point<f64>> {
+    align(time_series, simple_average(&closes(time_series), period))
+}
+
+/// Exponential moving average of the close prices over `period` entries, seeded with
+/// the simple moving average of the first `period` entries.
+pub fn ema(time_series: &TimeSeries, period: usize) -> Vec<Point<f64>> {
+    align(
+        time_series,
+        exponential_average(&closes(time_series), period),
+    )
+}
+
+/// Weighted moving average of the close prices over `period` entries, weighting the
+/// most recent entry by `period` and the oldest by 1.
+pub fn wma(time_series: &TimeSeries, period: usize) -> Vec<Point<f64>> {
+    if period == 0 {
+        return Vec::new();
+    }
+    let weights = (period * (period + 1) / 2) as f64;
+    let values = closes(time_series)
+        .windows(period)
+        .map(|window| {
+            let sum: f64 = window
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (i + 1) as f64 * value)
+                .sum();
+            sum / weights
+        })
+        .collect();
+    align(time_series, values)
+}
+
+/// Relative strength index of the close prices over `period` entries, using Wilder's
+/// smoothing.
+pub fn rsi(time_series: &TimeSeries, period: usize) -> Vec<Point<f64>> {
+    let closes = closes(time_series);
+    let changes: Vec<f64> = closes.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let gains: Vec<f64> = changes.iter().map(|change| change.max(0.0)).collect();
+    let losses: Vec<f64> = changes.iter().map(|change| (-change).max(0.0)).collect();
+    let values = wilder_average(&gains, period)
+        .into_iter()
+        .zip(wilder_average(&losses, period))
+        .map(|(gain, loss)| {
+            if loss == 0.0 {
+                100.0
+            } else {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            }
+        })
+        .collect();
+    align(time_series, values)
+}
+
+/// Moving average convergence/divergence of the close prices, using exponential moving
+/// averages over `fast`, `slow` and `signal` entries.
+pub fn macd(time_series: &TimeSeries, fast: usize, slow: usize, signal: usize) -> Vec<Point<Macd>> {
+    let closes = closes(time_series);
+    let fast = exponential_average(&closes, fast);
+    let slow = exponential_average(&closes, slow);
+    let len = fast.len().min(slow.len());
+    let lines: Vec<f64> = tail(&fast, len)
+        .iter()
+        .zip(tail(&slow, len))
+        .map(|(fast, slow)| fast - slow)
+        .collect();
+    let signals = exponential_average(&lines, signal);
+    let values = tail(&lines, signals.len())
+        .iter()
+        .zip(&signals)
+        .map(|(&macd, &signal)| Macd {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+        .collect();
+    align(time_series, values)
+}
+
+/// Bollinger Bands of the close prices, at `deviations` population standard deviations
+/// around the simple moving average over `period` entries.
+pub fn bollinger_bands(
+    time_series: &TimeSeries,
+    period: usize,
+    deviations: f64,
+) -> Vec<Point<BollingerBands>> {
+    if period == 0 {
+        return Vec::new();
+    }
+    let values = closes(time_series)
+        .windows(period)
+        .map(|window| {
+            let middle = mean(window);
+            let variance = window
+                .iter()
+                .map(|value| (value - middle).powi(2))
+                .sum::<f64>()
+                / period as f64;
+            let width = deviations * variance.sqrt();
+            BollingerBands {
+                upper: middle + width,
+                middle,
+                lower: middle - width,
+            }
+        })
+        .collect();
+    align(time_series, values)
+}
+
+/// Average true range over `period` entries, using Wilder's smoothing.
+pub fn atr(time_series: &TimeSeries, period: usize) -> Vec<Point<f64>> {
+    let true_ranges: Vec<f64> = time_series
+        .entries
+        .windows(2)
+        .map(|pair| {
+            let high = pair[1].high;
+            let low = pair[1].low;
+            let previous_close = pair[0].close;
+            (high - low)
+                .max((high - previous_close).abs())
+                .max((low - previous_close).abs())
+        })
+        .collect();
+    align(time_series, wilder_average(&true_ranges, period))
+}
+
+/// Stochastic oscillator, with a fast %K over `fast_k` entries smoothed by simple
+/// moving averages over `slow_k` entries for the slow %K and `slow_d` entries for the
+/// slow %D.
+pub fn stochastic(
+    time_series: &TimeSeries,
+    fast_k: usize,
+    slow_k: usize,
+    slow_d: usize,
+) -> Vec<Point<Stochastic>> {
+    if fast_k == 0 {
+        return Vec::new();
+    }
+    let fast: Vec<f64> = time_series
+        .entries
+        .windows(fast_k)
+        .map(|window| {
+            let close = window[fast_k - 1].close;
+            let (low, high) = window
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), entry| {
+                    (low.min(entry.low), high.max(entry.high))
+                });
+            if high > low {
+                100.0 * (close - low) / (high - low)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let k = simple_average(&fast, slow_k);
+    let d = simple_average(&k, slow_d);
+    let values = tail(&k, d.len())
+        .iter()
+        .zip(&d)
+        .map(|(&k, &d)| Stochastic { k, d })
+        .collect();
+    align(time_series, values)
+}
+
+/// On balance volume, starting from the volume of the first entry.
+pub fn obv(time_series: &TimeSeries) -> Vec<Point<i64>> {
+    let mut values = Vec::with_capacity(time_series.entries.len());
+    let mut previous: Option<(f64, i64)> = None;
+    for entry in &time_series.entries {
+        let close = entry.close;
+        let volume = entry.volume as i64;
+        let value = match previous {
+            Some((previous_close, obv)) if close > previous_close => obv + volume,
+            Some((previous_close, obv)) if close < previous_close => obv - volume,
+            Some((_, obv)) => obv,
+            None => volume,
+        };
+        values.push(value);
+        previous = Some((close, value));
+    }
+    align(time_series, values)
+}
+
+fn closes(time_series: &TimeSeries) -> Vec<f64> {
+    time_series
+        .entries
+        .iter()
+        .map(|entry| entry.close)
+        .collect()
+}
+
+/// Date values computed for the last entries of `time_series`.
+fn align<T>(time_series: &TimeSeries, values: Vec<T>) -> Vec<Point<T>> {
+    tail(&time_series.entries, values.len())
+        .iter()
+        .zip(values)
+        .map(|(entry, value)| Point {
+            date: entry.date,
+            value,
+        })
+        .collect()
+}
+
+fn tail<T>(values: &[T], len: usize) -> &[T] {
+    &values[values.len() - len.min(values.len())..]
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn simple_average(values: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 {
+        return Vec::new();
+    }
+    values.windows(period).map(mean).collect()
+}
+
+fn exponential_average(values: &[f64], period: usize) -> Vec<f64> {
+    smoothed_average(values, period, 2.0 / (period as f64 + 1.0))
+}
+
+fn wilder_average(values: &[f64], period: usize) -> Vec<f64> {
+    smoothed_average(values, period, 1.0 / period as f64)
+}
+
+/// Exponentially smoothed average with factor `alpha`, seeded with the mean of the
+/// first `period` values.
+fn smoothed_average(values: &[f64], period: usize, alpha: f64) -> Vec<f64> {
+    if period == 0 || values.len() < period {
+        return Vec::new();
+    }
+    let mut average = mean(&values[..period]);
+    let mut averages = vec![average];
+    for value in &values[period..] {
+        average += alpha * (value - average);
+        averages.push(average);
+    }
+    averages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use crate::time_series::{parser, Entry, Function};
+    use chrono::Duration;
+    use chrono_tz::US::Eastern;
+
+    /// Entries with the given high, low and close prices, with volumes of 100, 200, ...
+    fn series(prices: &[(f64, f64, f64)]) -> TimeSeries {
+        let start = parse_date("2018-06-01", Eastern).unwrap();
+        let entries: Vec<Entry> = prices
+            .iter()
+            .enumerate()
+            .map(|(i, (high, low, close))| Entry {
+                date: start + Duration::days(i as i64),
+                open: *close,
+                high: *high,
+                low: *low,
+                close: *close,
+                volume: 100 * (i as u64 + 1),
+                #[cfg(feature = "decimal")]
+                decimals: None,
+            })
+            .collect();
+        TimeSeries {
+            symbol: "MSFT".to_string(),
+            last_refreshed: entries.last().unwrap().date,
+            entries,
+        }
+    }
+
+    /// Closes of 10, 11, 12, 11, 13 and 14, including a gap up on the last entry.
+    fn time_series() -> TimeSeries {
+        series(&[
+            (11.0, 9.0, 10.0),
+            (12.0, 10.0, 11.0),
+            (13.0, 11.0, 12.0),
+            (12.5, 10.0, 11.0),
+            (13.5, 11.0, 13.0),
+            (15.0, 13.5, 14.0),
+        ])
+    }
+
+    /// Check `points` against hand computed values, given with the index of the entry
+    /// they are dated at.
+    fn check<T>(
+        time_series: &TimeSeries,
+        points: &[Point<T>],
+        expected: &[(usize, f64)],
+        value: impl Fn(&T) -> f64,
+    ) {
+        assert_eq!(points.len(), expected.len());
+        for (point, (index, expected)) in points.iter().zip(expected) {
+            assert_eq!(point.date, time_series.entries[*index].date);
+            assert!(
+                (value(&point.value) - expected).abs() < 1e-9,
+                "at {}: {} != {}",
+                index,
+                value(&point.value),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn moving_averages() {
+        let time_series = time_series();
+        check(
+            &time_series,
+            &sma(&time_series, 3),
+            &[(2, 11.0), (3, 34.0 / 3.0), (4, 12.0), (5, 38.0 / 3.0)],
+            |value| *value,
+        );
+        // The smoothing factor of a 3 entry average is 2 / (3 + 1) = 0.5.
+        check(
+            &time_series,
+            &ema(&time_series, 3),
+            &[(2, 11.0), (3, 11.0), (4, 12.0), (5, 13.0)],
+            |value| *value,
+        );
+        check(
+            &time_series,
+            &wma(&time_series, 3),
+            &[
+                (2, 68.0 / 6.0),
+                (3, 68.0 / 6.0),
+                (4, 73.0 / 6.0),
+                (5, 79.0 / 6.0),
+            ],
+            |value| *value,
+        );
+    }
+
+    #[test]
+    fn oscillators() {
+        let time_series = time_series();
+        // Average gains of 2/3, 10/9 and 29/27 against losses of 1/3, 2/9 and 4/27.
+        check(
+            &time_series,
+            &rsi(&time_series, 3),
+            &[
+                (3, 100.0 - 100.0 / 3.0),
+                (4, 100.0 - 100.0 / 6.0),
+                (5, 100.0 - 100.0 / 8.25),
+            ],
+            |value| *value,
+        );
+
+        // Fast averages of 11.5, 67/6, 223/18 and 727/54 against slow averages of 11,
+        // 11, 12 and 13.
+        let macd = macd(&time_series, 2, 3, 2);
+        let lines = [(3, 1.0 / 6.0), (4, 7.0 / 18.0), (5, 25.0 / 54.0)];
+        let signals = [(3, 1.0 / 3.0), (4, 10.0 / 27.0), (5, 35.0 / 81.0)];
+        let histograms = [(3, -1.0 / 6.0), (4, 1.0 / 54.0), (5, 5.0 / 162.0)];
+        check(&time_series, &macd, &lines, |value| value.macd);
+        check(&time_series, &macd, &signals, |value| value.signal);
+        check(&time_series, &macd, &histograms, |value| value.histogram);
+
+        // Fast %K of 75, 100/3, 600/7 and 80.
+        let stochastic = stochastic(&time_series, 3, 2, 2);
+        let k = [(4, 1250.0 / 21.0), (5, 580.0 / 7.0)];
+        let d = [(4, (325.0 / 6.0 + 1250.0 / 21.0) / 2.0), (5, 1495.0 / 21.0)];
+        check(&time_series, &stochastic, &k, |value| value.k);
+        check(&time_series, &stochastic, &d, |value| value.d);
+    }
+
+    #[test]
+    fn volatility_and_volume() {
+        let time_series = time_series();
+        let bands = bollinger_bands(&time_series, 3, 2.0);
+        let first = 2.0 * (2.0f64 / 3.0).sqrt();
+        let last = 2.0 * 14f64.sqrt() / 3.0;
+        check(&time_series, &bands[..1], &[(2, 11.0 + first)], |value| {
+            value.upper
+        });
+        check(
+            &time_series,
+            &bands[3..],
+            &[(5, 38.0 / 3.0 - last)],
+            |value| value.lower,
+        );
+        check(
+            &time_series,
+            &bands,
+            &[(2, 11.0), (3, 34.0 / 3.0), (4, 12.0), (5, 38.0 / 3.0)],
+            |value| value.middle,
+        );
+
+        // True ranges of 2, 2, 2.5, 2.5 and 2, the last one from the previous close.
+        check(
+            &time_series,
+            &atr(&time_series, 3),
+            &[(3, 6.5 / 3.0), (4, 41.0 / 18.0), (5, 59.0 / 27.0)],
+            |value| *value,
+        );
+        check(
+            &time_series,
+            &obv(&time_series),
+            &[
+                (0, 100.0),
+                (1, 300.0),
+                (2, 600.0),
+                (3, 200.0),
+                (4, 700.0),
+                (5, 1300.0),
+            ],
+            |value| *value as f64,
+        );
+    }
+
+    #[test]
+    fn sma_matches_api_response() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_daily.json");
+        let time_series = parser::parse(&Function::Daily, data).unwrap();
+        let points = sma(&time_series, 10);
+        let response: serde_json::Value =
+            serde_json::from_slice(include_bytes!("../tests/json/indicator_sma.json")).unwrap();
+        let values = response["Technical Analysis: SMA"].as_object().unwrap();
+        assert!(!values.is_empty());
+        for (date, value) in values {
+            let date = parse_date(date, Eastern).unwrap();
+            let expected: f64 = value["SMA"].as_str().unwrap().parse().unwrap();
+            let point = points.iter().find(|point| point.date == date).unwrap();
+            assert!(
+                (point.value - expected).abs() < 5e-5,
+                "at {}: {} != {}",
+                date,
+                point.value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn insufficient_entries() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_daily.json");
+        let mut time_series = parser::parse(&Function::Daily, data).unwrap();
+        time_series.entries.truncate(5);
+        assert!(sma(&time_series, 10).is_empty());
+        assert!(rsi(&time_series, 5).is_empty());
+        assert!(macd(&time_series, 12, 26, 9).is_empty());
+        assert!(sma(&time_series, 0).is_empty());
+        assert_eq!(sma(&time_series, 5).len(), 1);
+    }
+}
@@ -1,18 +1,17 @@
-#[cfg(feature = "blocking")]
-use reqwest::blocking::Request as BlockingRequest;
-use reqwest::{Method, Request, Url};
-use std::convert::From;
+use reqwest::Url;
 
-const URL_ENDPOINT: &str = "https://www.alphavantage.co/query";
+pub(crate) const URL_ENDPOINT: &str = "https://www.alphavantage.co/query";
 
 pub(crate) struct APIRequestBuilder {
     key: String,
+    base_url: Url,
 }
 
 impl APIRequestBuilder {
-    pub(crate) fn new(key: &str) -> APIRequestBuilder {
+    pub(crate) fn new(key: &str, base_url: Url) -> APIRequestBuilder {
         APIRequestBuilder {
             key: String::from(key),
+            base_url,
         }
     }
 
@@ -21,27 +20,34 @@ impl APIRequestBuilder {
         function: &'a str,
         params: &'a [(&'a str, &'a str)],
     ) -> APIRequest<'a> {
-        APIRequest::new(&self.key, function, params)
+        APIRequest::new(&self.base_url, &self.key, function, params)
     }
 }
 
 pub(crate) struct APIRequest<'a> {
+    base_url: &'a Url,
     key: &'a str,
     function: &'a str,
     params: &'a [(&'a str, &'a str)],
 }
 
 impl<'a> APIRequest<'a> {
-    fn new(key: &'a str, function: &'a str, params: &'a [(&'a str, &'a str)]) -> APIRequest<'a> {
+    fn new(
+        base_url: &'a Url,
+        key: &'a str,
+        function: &'a str,
+        params: &'a [(&'a str, &'a str)],
+    ) -> APIRequest<'a> {
         APIRequest {
+            base_url,
             key,
             function,
             params,
         }
     }
 
-    fn url(&self) -> Url {
-        let mut url = Url::parse(URL_ENDPOINT).unwrap();
+    pub(crate) fn url(&self) -> Url {
+        let mut url = self.base_url.clone();
         {
             let mut query = url.query_pairs_mut();
             query.append_pair("function", self.function);
@@ -54,15 +60,19 @@ impl<'a> APIRequest<'a> {
     }
 }
 
-impl From<APIRequest<'_>> for Request {
-    fn from(request: APIRequest) -> Self {
-        reqwest::Request::new(Method::GET, request.url())
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[cfg(feature = "blocking")]
-impl<'a> From<APIRequest<'a>> for BlockingRequest {
-    fn from(request: APIRequest) -> Self {
-        reqwest::blocking::Request::new(Method::GET, request.url())
+    #[test]
+    fn url() {
+        let base_url = Url::parse("http://localhost:8080/query").unwrap();
+        let builder = APIRequestBuilder::new("secret", base_url);
+        let params = [("symbol", "MSFT"), ("outputsize", "compact")];
+        let request = builder.create("TIME_SERIES_DAILY", &params);
+        assert_eq!(
+            request.url().as_str(),
+            "http://localhost:8080/query?function=TIME_SERIES_DAILY&apikey=secret&symbol=MSFT&outputsize=compact"
+        );
     }
 }
@@ -1,139 +1,440 @@
 //! Blocking client implementation
 use crate::api::APIRequestBuilder;
+use crate::cache::{Cache, ResponseCache, TtlPolicy};
+use crate::config::ClientConfig;
+use crate::deserialize::check_status;
 use crate::error::Error;
-use crate::exchange_rate;
+use crate::exchange_rate::{self, CurrencyCode};
+use crate::rate_limit::{Budget, RateLimit, RateLimiter};
+use crate::request::{ApiRequest, RawRequest};
+use crate::retry::RetryPolicy;
+use crate::symbol::Symbol;
+use crate::tickers;
 use crate::time_series;
-use std::io::Read;
+use crate::transport::{parse_retry_after, HttpResponse, MockTransport};
+use reqwest::header::HeaderMap;
+use reqwest::{Proxy, Url};
+use std::thread;
+use std::time::Duration;
+
+/// A blocking HTTP transport.
+pub trait Transport: Send + Sync {
+    /// Send a GET request for the fully built `url` (including the API key).
+    fn fetch(&self, url: &Url) -> Result<HttpResponse, Error>;
+}
+
+impl Transport for reqwest::blocking::Client {
+    fn fetch(&self, url: &Url) -> Result<HttpResponse, Error> {
+        let response = self.get(url.clone()).send()?;
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.bytes()?.to_vec();
+        Ok(HttpResponse {
+            status,
+            body,
+            retry_after,
+        })
+    }
+}
+
+impl Transport for MockTransport {
+    fn fetch(&self, url: &Url) -> Result<HttpResponse, Error> {
+        Ok(self.respond(url))
+    }
+}
 
 /// A blocking client for the Alpha Vantage API.
 pub struct Client {
     builder: APIRequestBuilder,
-    client: reqwest::blocking::Client,
+    transport: Box<dyn Transport>,
+    limiter: Option<RateLimiter>,
+    retry_policy: Option<RetryPolicy>,
+    cache: Option<ResponseCache>,
 }
 
 impl Client {
     /// Create a new blocking client using the specified API `key`.
     pub fn new(key: &str) -> Client {
-        Client {
-            builder: APIRequestBuilder::new(key),
-            client: reqwest::blocking::Client::new(),
-        }
+        Client::builder(key)
+            .build()
+            .expect("failed to create client")
+    }
+
+    /// Create a builder to configure a blocking client using the specified API `key`.
+    pub fn builder(key: &str) -> ClientBuilder {
+        ClientBuilder::new(key)
+    }
+
+    /// Limit the rate of calls made by the client.
+    ///
+    /// Equivalent to [ClientBuilder::rate_limit], which should be preferred when
+    /// creating a blocking client.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Client {
+        self.limiter = Some(RateLimiter::new(rate_limit));
+        self
+    }
+
+    /// Retry calls failing due to transient errors according to `retry_policy`.
+    ///
+    /// Equivalent to [ClientBuilder::retry_policy], which should be preferred when
+    /// creating a blocking client.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Client {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Number of calls which can currently be made without waiting, if rate limited.
+    pub fn remaining_budget(&self) -> Option<Budget> {
+        self.limiter.as_ref().map(RateLimiter::remaining)
     }
 
     /// Retrieve intraday time series for the specified `symbol` updated in realtime (latest 100 data points).
     pub fn get_time_series_intraday(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
         interval: time_series::IntradayInterval,
     ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::IntraDay(interval);
-        self.get_time_series(&function, symbol, time_series::OutputSize::Compact)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Compact)
     }
 
     /// Retrieve intraday time series for the specified `symbol` updated in realtime (full data set).
     pub fn get_time_series_intraday_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
         interval: time_series::IntradayInterval,
     ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::IntraDay(interval);
-        self.get_time_series(&function, symbol, time_series::OutputSize::Full)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Full)
     }
 
     /// Retrieve daily time series for the specified `symbol` (latest 100 data points).
-    pub fn get_time_series_daily(&self, symbol: &str) -> Result<time_series::TimeSeries, Error> {
+    pub fn get_time_series_daily(
+        &self,
+        symbol: impl Into<Symbol>,
+    ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Daily;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Compact)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Compact)
     }
 
     /// Retrieve daily time series for the specified `symbol` (full data set).
     pub fn get_time_series_daily_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Daily;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Full)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Full)
     }
 
     /// Retrieve weekly time series for the specified `symbol` (latest 100 data points).
-    pub fn get_time_series_weekly(&self, symbol: &str) -> Result<time_series::TimeSeries, Error> {
+    pub fn get_time_series_weekly(
+        &self,
+        symbol: impl Into<Symbol>,
+    ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Weekly;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Compact)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Compact)
     }
 
     /// Retrieve weekly time series for the specified `symbol` (full data set).
     pub fn get_time_series_weekly_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Weekly;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Full)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Full)
     }
 
     /// Retrieve monthly time series for the specified `symbol` (latest 100 data points).
-    pub fn get_time_series_monthly(&self, symbol: &str) -> Result<time_series::TimeSeries, Error> {
+    pub fn get_time_series_monthly(
+        &self,
+        symbol: impl Into<Symbol>,
+    ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Monthly;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Compact)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Compact)
     }
 
     /// Retrieve monthly time series for the specified `symbol` (full data set).
     pub fn get_time_series_monthly_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         let function = time_series::Function::Monthly;
-        self.get_time_series(&function, symbol, time_series::OutputSize::Full)
+        self.get_time_series(&function, &symbol.into(), time_series::OutputSize::Full)
     }
 
     /// Retrieve the exchange rate from the currency specified by `from_currency_code` to the
     /// currency specified by `to_currency_code`.
     pub fn get_exchange_rate(
         &self,
-        from_currency_code: &str,
-        to_currency_code: &str,
+        from_currency_code: impl Into<CurrencyCode>,
+        to_currency_code: impl Into<CurrencyCode>,
     ) -> Result<exchange_rate::ExchangeRate, Error> {
-        let function = "CURRENCY_EXCHANGE_RATE";
-        let params = vec![
-            ("from_currency", from_currency_code),
-            ("to_currency", to_currency_code),
-        ];
-        let response = self.api_call(function, &params)?;
-        let result = exchange_rate::parser::parse(response)?;
-        Ok(result)
+        let (from, to) = (from_currency_code.into(), to_currency_code.into());
+        from.validate()?;
+        to.validate()?;
+        let request = exchange_rate::ExchangeRateRequest::new(from.as_str(), to.as_str());
+        self.execute(&request)
     }
 
     /// Retrieve a list of ticker symbols that match the specified `query`.
     pub fn get_tickers(&self, query: &str) -> Result<tickers::SearchResults, Error> {
-        let function = "SYMBOL_SEARCH";
-        let params = vec![("keywords", query)];
-        let request = self.builder.create(function, &params);
-        let response = self.api_call(request)?;
-        let result = tickers::parser::parse(Some(query.to_string()), response)?;
-        Ok(result)
+        let request = tickers::SearchRequest::new(query);
+        self.execute(&request)
+    }
+
+    /// Execute the specified `request` and parse its response.
+    pub fn execute<R: ApiRequest>(&self, request: &R) -> Result<R::Response, Error> {
+        let function = request.function();
+        let params = request.params();
+        if let Some(cache) = &self.cache {
+            if let Some(body) = cache.get(function, &params) {
+                if let Ok(response) = request.parse(body.as_slice()) {
+                    return Ok(response);
+                }
+            }
+        }
+        let mut attempt = 1;
+        loop {
+            let result = self.api_call(function, &params).and_then(|body| {
+                check_status(&body)?;
+                let response = request.parse(body.as_slice())?;
+                if let Some(cache) = &self.cache {
+                    cache.insert(function, &params, &body);
+                }
+                Ok(response)
+            });
+            let error = match result {
+                Err(error) => error,
+                response => return response,
+            };
+            let delay = self
+                .retry_policy
+                .and_then(|policy| policy.retry_delay(attempt, &error));
+            match delay {
+                Some(delay) => thread::sleep(delay),
+                None => return Err(error),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Call the specified API `function` with the given `params` and return the unparsed
+    /// JSON response.
+    pub fn call_raw(
+        &self,
+        function: &str,
+        params: &[(&str, &str)],
+    ) -> Result<serde_json::Value, Error> {
+        self.execute(&RawRequest::new(function, params))
     }
 
     fn get_time_series(
         &self,
         function: &time_series::Function,
-        symbol: &str,
+        symbol: &Symbol,
         output_size: time_series::OutputSize,
     ) -> Result<time_series::TimeSeries, Error> {
-        let mut params = vec![("symbol", symbol), ("outputsize", output_size.to_string())];
-        if let time_series::Function::IntraDay(interval) = function {
-            params.push(("interval", interval.to_string()));
+        symbol.validate()?;
+        let request = time_series::TimeSeriesRequest::new(*function, symbol.as_str(), output_size);
+        self.execute(&request)
+    }
+
+    fn api_call(&self, function: &str, params: &[(&str, &str)]) -> Result<Vec<u8>, Error> {
+        if let Some(limiter) = &self.limiter {
+            thread::sleep(limiter.reserve()?);
         }
-        let response = self.api_call(function.into(), &params)?;
-        let result = time_series::parser::parse(function, response)?;
-        Ok(result)
+        let url = self.builder.create(function, params).url();
+        let response = self.transport.fetch(&url)?;
+        match response.status {
+            200 => {}
+            429 => return Err(Error::TooManyRequests(response.retry_after)),
+            status => return Err(Error::ServerError(status)),
+        }
+        Ok(response.body)
     }
+}
 
-    fn api_call(&self, function: &str, params: &[(&str, &str)]) -> Result<impl Read, Error> {
-        let request = self.builder.create(function, params);
-        let response = self.client.execute(request.into())?;
-        let status = response.status();
-        if status != reqwest::StatusCode::OK {
-            return Err(Error::ServerError(status.as_u16()));
+/// A builder to configure a blocking client.
+pub struct ClientBuilder {
+    config: ClientConfig,
+    transport: Option<Box<dyn Transport>>,
+}
+
+impl ClientBuilder {
+    /// Create a builder using the specified API `key`.
+    pub fn new(key: &str) -> ClientBuilder {
+        ClientBuilder {
+            config: ClientConfig::new(key),
+            transport: None,
         }
-        Ok(response)
+    }
+
+    /// Send requests to `base_url` instead of the Alpha Vantage endpoint
+    /// (e.g. a caching proxy or a mock server).
+    pub fn base_url(mut self, base_url: &str) -> ClientBuilder {
+        self.config.base_url = base_url.to_string();
+        self
+    }
+
+    /// Set the `timeout` for each request.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with each request.
+    pub fn user_agent(mut self, user_agent: &str) -> ClientBuilder {
+        self.config.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Send requests through the specified `proxy`.
+    pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the headers sent with each request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+        self.config.default_headers = headers;
+        self
+    }
+
+    /// Use a preconfigured HTTP `client`. The timeout, user agent, proxy and default
+    /// headers set on the builder are ignored in that case.
+    pub fn http_client(self, client: reqwest::blocking::Client) -> ClientBuilder {
+        self.transport(client)
+    }
+
+    /// Send requests through the specified `transport`. The timeout, user agent, proxy
+    /// and default headers set on the builder are ignored in that case.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> ClientBuilder {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Limit the rate of calls made by the client. Calls exceeding the limit
+    /// block until enough budget is available.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> ClientBuilder {
+        self.config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Retry calls failing due to transient errors according to `retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> ClientBuilder {
+        self.config.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Cache responses in `cache` for the time determined by `ttl_policy`.
+    pub fn cache(mut self, cache: impl Cache + 'static, ttl_policy: TtlPolicy) -> ClientBuilder {
+        self.config.cache = Some(ResponseCache::new(Box::new(cache), ttl_policy));
+        self
+    }
+
+    /// Create the client.
+    pub fn build(self) -> Result<Client, Error> {
+        let config = self.config;
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                let mut builder = reqwest::blocking::ClientBuilder::new()
+                    .default_headers(config.default_headers.clone());
+                if let Some(timeout) = config.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(user_agent) = &config.user_agent {
+                    builder = builder.user_agent(user_agent.as_str());
+                }
+                if let Some(proxy) = &config.proxy {
+                    builder = builder.proxy(proxy.clone());
+                }
+                let client = builder
+                    .build()
+                    .map_err(|e| Error::ConfigurationError(e.to_string()))?;
+                Box::new(client)
+            }
+        };
+        Ok(Client {
+            builder: config.request_builder()?,
+            transport,
+            limiter: config.limiter(),
+            retry_policy: config.retry_policy,
+            cache: config.cache,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_response() {
+        let transport = MockTransport::new().with_response(
+            "SYMBOL_SEARCH",
+            &[("keywords", "tesco")],
+            HttpResponse::ok(&include_bytes!("../tests/json/ticker_search_tesco.json")[..]),
+        );
+        let client = Client::builder("secret")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let results = client.get_tickers("tesco").unwrap();
+        assert_eq!(results.query, Some("tesco".to_string()));
+        assert_eq!(results.entries.len(), 5);
+    }
+
+    struct TextRequest;
+
+    impl ApiRequest for TextRequest {
+        type Response = String;
+
+        fn function(&self) -> &str {
+            "TIME_SERIES_DAILY"
+        }
+
+        fn params(&self) -> Vec<(&str, &str)> {
+            vec![("symbol", "MSFT")]
+        }
+
+        fn parse<R: std::io::Read>(&self, mut reader: R) -> Result<String, Error> {
+            let mut text = String::new();
+            reader
+                .read_to_string(&mut text)
+                .map_err(|e| Error::ParsingError(e.to_string()))?;
+            Ok(text)
+        }
+    }
+
+    #[test]
+    fn custom_requests_detect_rate_limits() {
+        let transport = MockTransport::new().with_response(
+            "TIME_SERIES_DAILY",
+            &[("symbol", "MSFT")],
+            HttpResponse::ok(&include_bytes!("../tests/json/rate_limit_note.json")[..]),
+        );
+        let client = Client::builder("secret")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let result = client.execute(&TextRequest);
+        assert!(matches!(result, Err(Error::RateLimited(_))));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let client = Client::builder("secret")
+            .transport(MockTransport::new())
+            .build()
+            .unwrap();
+        let result = client.get_time_series_weekly("");
+        assert!(matches!(result, Err(Error::InvalidSymbol(_))));
+        let result = client.get_exchange_rate("BTC", "XYZ");
+        assert!(matches!(result, Err(Error::InvalidCurrencyCode(_))));
     }
 }
@@ -0,0 +1,366 @@
+//! Caching of API responses
+//!
+//! A [Cache] can be set on a client so that responses are reused for a time
+//! depending on the API function, as determined by a [TtlPolicy]. Responses are
+//! keyed by function and parameters, never by API key, and are only cached once
+//! they have been successfully parsed.
+//!
+//! [MemoryCache] keeps responses for the lifetime of the process, while
+//! [FileCache] stores them in a directory so they survive restarts.
+use crate::error::Error;
+use crate::fixtures::{file_name, Body};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Function and parameters identifying a cached response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    function: String,
+    params: Vec<(String, String)>,
+}
+
+impl CacheKey {
+    /// Create a key for calls to `function` with the specified `params`, in any order.
+    pub fn new(function: &str, params: &[(&str, &str)]) -> CacheKey {
+        let mut params: Vec<(String, String)> = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        params.sort();
+        CacheKey {
+            function: function.to_string(),
+            params,
+        }
+    }
+
+    /// Name of the API function.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Parameters sorted by name.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+/// Storage for cached responses.
+pub trait Cache: Send + Sync {
+    /// Return the response body cached for `key`, unless it has expired.
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>>;
+
+    /// Cache the response `body` for `key` until `expires_at`.
+    fn insert(&self, key: &CacheKey, body: &[u8], expires_at: SystemTime);
+}
+
+impl<C: Cache + ?Sized> Cache for Arc<C> {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        (**self).get(key)
+    }
+
+    fn insert(&self, key: &CacheKey, body: &[u8], expires_at: SystemTime) {
+        (**self).insert(key, body, expires_at)
+    }
+}
+
+/// Time to live of cached responses for each API function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtlPolicy {
+    default: Duration,
+    functions: HashMap<String, Duration>,
+}
+
+impl Default for TtlPolicy {
+    /// Cache quotes and intraday series for a minute, daily series for a few hours,
+    /// and fundamentals and searches for a day. Other functions are not cached.
+    fn default() -> TtlPolicy {
+        let functions = [
+            ("GLOBAL_QUOTE", MINUTE),
+            ("CURRENCY_EXCHANGE_RATE", MINUTE),
+            ("TIME_SERIES_INTRADAY", MINUTE),
+            ("TIME_SERIES_DAILY", 4 * HOUR),
+            ("TIME_SERIES_DAILY_ADJUSTED", 4 * HOUR),
+            ("TIME_SERIES_WEEKLY", 12 * HOUR),
+            ("TIME_SERIES_WEEKLY_ADJUSTED", 12 * HOUR),
+            ("TIME_SERIES_MONTHLY", 12 * HOUR),
+            ("TIME_SERIES_MONTHLY_ADJUSTED", 12 * HOUR),
+            ("SYMBOL_SEARCH", DAY),
+            ("OVERVIEW", DAY),
+            ("INCOME_STATEMENT", DAY),
+            ("BALANCE_SHEET", DAY),
+            ("CASH_FLOW", DAY),
+            ("EARNINGS", DAY),
+        ];
+        TtlPolicy {
+            default: Duration::ZERO,
+            functions: functions
+                .iter()
+                .map(|(function, ttl)| (function.to_string(), *ttl))
+                .collect(),
+        }
+    }
+}
+
+impl TtlPolicy {
+    /// Create a policy caching all functions for `ttl`.
+    pub fn new(ttl: Duration) -> TtlPolicy {
+        TtlPolicy {
+            default: ttl,
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Cache responses for `function` for `ttl`. A zero `ttl` disables caching.
+    pub fn function(mut self, function: &str, ttl: Duration) -> TtlPolicy {
+        self.functions.insert(function.to_string(), ttl);
+        self
+    }
+
+    /// Cache responses for functions without a specific time to live for `ttl`.
+    pub fn default_ttl(mut self, ttl: Duration) -> TtlPolicy {
+        self.default = ttl;
+        self
+    }
+
+    /// Time to live of responses for `function`.
+    pub fn ttl(&self, function: &str) -> Duration {
+        self.functions
+            .get(function)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Cache along with the policy determining how long responses are kept.
+pub(crate) struct ResponseCache {
+    cache: Box<dyn Cache>,
+    ttl_policy: TtlPolicy,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(cache: Box<dyn Cache>, ttl_policy: TtlPolicy) -> ResponseCache {
+        ResponseCache { cache, ttl_policy }
+    }
+
+    pub(crate) fn get(&self, function: &str, params: &[(&str, &str)]) -> Option<Vec<u8>> {
+        if self.ttl_policy.ttl(function).is_zero() {
+            return None;
+        }
+        self.cache.get(&CacheKey::new(function, params))
+    }
+
+    pub(crate) fn insert(&self, function: &str, params: &[(&str, &str)], body: &[u8]) {
+        let ttl = self.ttl_policy.ttl(function);
+        if !ttl.is_zero() {
+            let key = CacheKey::new(function, params);
+            self.cache.insert(&key, body, SystemTime::now() + ttl);
+        }
+    }
+}
+
+/// An in-memory cache.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<CacheKey, (SystemTime, Vec<u8>)>>,
+}
+
+impl MemoryCache {
+    /// Create an empty cache.
+    pub fn new() -> MemoryCache {
+        MemoryCache::default()
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((expires_at, body)) if *expires_at > SystemTime::now() => Some(body.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: &CacheKey, body: &[u8], expires_at: SystemTime) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(key.clone(), (expires_at, body.to_vec()));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileEntry {
+    function: String,
+    params: Vec<(String, String)>,
+    expires_at: u64,
+    body: Body,
+}
+
+/// A cache storing responses as files in a directory.
+///
+/// Errors reading or writing files are ignored and treated as cache misses.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    dir: PathBuf,
+}
+
+impl FileCache {
+    /// Create a cache storing responses in the directory `dir`, which is created if needed.
+    pub fn new(dir: impl AsRef<Path>) -> Result<FileCache, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| {
+            Error::StorageError(format!("unable to create {}: {}", dir.display(), e))
+        })?;
+        Ok(FileCache { dir })
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(file_name(&key.function, &key.params))
+    }
+}
+
+impl Cache for FileCache {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let json = fs::read(&path).ok()?;
+        let entry: FileEntry = serde_json::from_slice(&json).ok()?;
+        if entry.function != key.function || entry.params != key.params {
+            return None;
+        }
+        let expires_at = UNIX_EPOCH + Duration::from_secs(entry.expires_at);
+        if expires_at <= SystemTime::now() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.body.into_bytes())
+    }
+
+    fn insert(&self, key: &CacheKey, body: &[u8], expires_at: SystemTime) {
+        let entry = FileEntry {
+            function: key.function.clone(),
+            params: key.params.clone(),
+            expires_at: expires_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            body: Body::new(body),
+        };
+        if let Ok(json) = serde_json::to_vec(&entry) {
+            let _ = fs::write(self.path(key), json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpResponse, MockTransport};
+    use crate::Client;
+
+    fn key() -> CacheKey {
+        CacheKey::new(
+            "TIME_SERIES_DAILY",
+            &[("symbol", "MSFT"), ("outputsize", "full")],
+        )
+    }
+
+    #[test]
+    fn ttl_policy() {
+        let policy = TtlPolicy::default().function("GLOBAL_QUOTE", Duration::from_secs(5));
+        assert_eq!(policy.ttl("GLOBAL_QUOTE"), Duration::from_secs(5));
+        assert_eq!(policy.ttl("TIME_SERIES_DAILY"), 4 * HOUR);
+        assert_eq!(policy.ttl("NEWS_SENTIMENT"), Duration::ZERO);
+        let policy = policy.default_ttl(MINUTE);
+        assert_eq!(policy.ttl("NEWS_SENTIMENT"), MINUTE);
+    }
+
+    #[test]
+    fn memory_cache_expiry() {
+        let cache = MemoryCache::new();
+        let now = SystemTime::now();
+        cache.insert(&key(), b"body", now + MINUTE);
+        assert_eq!(cache.get(&key()), Some(b"body".to_vec()));
+        cache.insert(&key(), b"body", now - MINUTE);
+        assert_eq!(cache.get(&key()), None);
+    }
+
+    #[test]
+    fn file_cache_survives_restarts() {
+        let dir = std::env::temp_dir().join(format!("alphavantage-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = FileCache::new(&dir).unwrap();
+        cache.insert(&key(), b"body", SystemTime::now() + MINUTE);
+        let cache = FileCache::new(&dir).unwrap();
+        assert_eq!(cache.get(&key()), Some(b"body".to_vec()));
+        let binary = vec![0x00, 0xff, 0xfe];
+        cache.insert(&key(), &binary, SystemTime::now() + MINUTE);
+        assert_eq!(cache.get(&key()), Some(binary));
+        let other = CacheKey::new("TIME_SERIES_DAILY", &[("symbol", "AAPL")]);
+        assert_eq!(cache.get(&other), None);
+
+        let file = dir.join("file");
+        fs::write(&file, b"").unwrap();
+        let result = FileCache::new(file.join("cache"));
+        assert!(matches!(result, Err(Error::StorageError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn client_uses_cache() {
+        let cache = Arc::new(MemoryCache::new());
+        let transport = MockTransport::new().with_response(
+            "CURRENCY_EXCHANGE_RATE",
+            &[("from_currency", "EUR"), ("to_currency", "USD")],
+            HttpResponse::ok(&include_bytes!("../tests/json/currency_exchange_rate.json")[..]),
+        );
+        let client = Client::builder("secret")
+            .transport(transport)
+            .cache(cache.clone(), TtlPolicy::default())
+            .build()
+            .unwrap();
+        let fetched = client.get_exchange_rate("EUR", "USD").await.unwrap();
+
+        let offline = Client::builder("secret")
+            .transport(MockTransport::new())
+            .cache(cache, TtlPolicy::default())
+            .build()
+            .unwrap();
+        let cached = offline.get_exchange_rate("EUR", "USD").await.unwrap();
+        assert_eq!(fetched, cached);
+        assert!(offline.get_exchange_rate("USD", "EUR").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn client_caches_parsed_responses_only() {
+        let cache = Arc::new(MemoryCache::new());
+        let params = [("from_currency", "EUR"), ("to_currency", "USD")];
+        let transport = MockTransport::new().with_response(
+            "CURRENCY_EXCHANGE_RATE",
+            &params,
+            HttpResponse::ok(r#"{"Unexpected": {}}"#),
+        );
+        let client = Client::builder("secret")
+            .transport(transport)
+            .cache(cache.clone(), TtlPolicy::default())
+            .build()
+            .unwrap();
+        let key = CacheKey::new("CURRENCY_EXCHANGE_RATE", &params);
+        let result = client.get_exchange_rate("EUR", "USD").await;
+        assert!(matches!(result, Err(Error::ParsingError(_))));
+        assert_eq!(cache.get(&key), None);
+        client
+            .call_raw("CURRENCY_EXCHANGE_RATE", &params)
+            .await
+            .unwrap();
+        assert!(cache.get(&key).is_some());
+    }
+}
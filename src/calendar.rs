@@ -0,0 +1,484 @@
+//! Trading calendars, describing the days and hours during which a market is open.
+//!
+//! Calendars for major exchanges are provided by [Exchange], with their holidays and
+//! early closes computed from the rules of each exchange. Exceptional closures (e.g.
+//! national days of mourning) are not included.
+use crate::time_series::{Entry, TimeSeries};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+/// Maximum number of days searched for the next session.
+const MAX_SESSION_SEARCH_DAYS: u32 = 30;
+
+/// Days and regular trading hours of a market.
+pub trait TradingCalendar {
+    /// Return the time zone of the market.
+    fn time_zone(&self) -> Tz;
+
+    /// Return whether the market is open on `date`, in the time zone of the market.
+    fn is_trading_day(&self, date: NaiveDate) -> bool;
+
+    /// Return the opening and closing times of the regular session on `date`, or `None`
+    /// if the market is closed on that date.
+    fn session_bounds(&self, date: NaiveDate) -> Option<(DateTime<Tz>, DateTime<Tz>)>;
+
+    /// Return the opening and closing times of the first regular session opening after
+    /// `date`, or `None` if there is none within 30 days.
+    fn next_session<T: TimeZone>(&self, date: &DateTime<T>) -> Option<(DateTime<Tz>, DateTime<Tz>)>
+    where
+        Self: Sized,
+    {
+        let date = date.with_timezone(&self.time_zone());
+        date.date_naive()
+            .iter_days()
+            .take(MAX_SESSION_SEARCH_DAYS as usize)
+            .filter_map(|day| self.session_bounds(day))
+            .find(|(open, _)| *open > date)
+    }
+
+    /// Return the session of an intraday entry dated at the end of its interval, or
+    /// `None` if the market is closed on that date.
+    fn session<T: TimeZone>(&self, date: &DateTime<T>) -> Option<Session>
+    where
+        Self: Sized,
+    {
+        let date = date.with_timezone(&self.time_zone());
+        let (open, close) = self.session_bounds(date.date_naive())?;
+        Some(if date <= open {
+            Session::PreMarket
+        } else if date <= close {
+            Session::Regular
+        } else {
+            Session::AfterHours
+        })
+    }
+}
+
+/// Represents the trading session of an intraday entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Session {
+    /// Before the opening of the regular session.
+    PreMarket,
+    /// During the regular session.
+    Regular,
+    /// After the closing of the regular session.
+    AfterHours,
+}
+
+/// Tag the entries of an intraday time series with their session, or `None` for
+/// entries dated on days the market is closed.
+pub fn tag_sessions<'a>(
+    time_series: &'a TimeSeries,
+    calendar: &impl TradingCalendar,
+) -> Vec<(&'a Entry, Option<Session>)> {
+    time_series
+        .entries
+        .iter()
+        .map(|entry| (entry, calendar.session(&entry.date)))
+        .collect()
+}
+
+/// Represents a stock exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exchange {
+    /// New York Stock Exchange, whose calendar is shared by the Nasdaq Stock Market and
+    /// is used for all United States listings.
+    Nyse,
+    /// London Stock Exchange.
+    Lse,
+    /// Xetra (Deutsche Börse).
+    Xetra,
+    /// Frankfurt Stock Exchange.
+    Frankfurt,
+}
+
+impl Exchange {
+    /// Return the exchange of a region, as reported in [crate::tickers::Entry].
+    pub fn from_region(region: &str) -> Option<Exchange> {
+        match region {
+            "United States" => Some(Exchange::Nyse),
+            "United Kingdom" => Some(Exchange::Lse),
+            "XETRA" => Some(Exchange::Xetra),
+            "Frankfurt" => Some(Exchange::Frankfurt),
+            _ => None,
+        }
+    }
+
+    /// Opening and closing times of the regular session.
+    fn hours(self) -> (NaiveTime, NaiveTime) {
+        match self {
+            Exchange::Nyse => (time(9, 30), time(16, 0)),
+            Exchange::Lse => (time(8, 0), time(16, 30)),
+            Exchange::Xetra => (time(9, 0), time(17, 30)),
+            Exchange::Frankfurt => (time(8, 0), time(22, 0)),
+        }
+    }
+
+    /// Closing time on days the exchange closes early, if any.
+    fn early_close(self, date: NaiveDate) -> Option<NaiveTime> {
+        let (month, day) = (date.month(), date.day());
+        match self {
+            Exchange::Nyse => {
+                let independence_day_eve = month == 7
+                    && day == 3
+                    && !matches!(date.succ_opt()?.weekday(), Weekday::Sat | Weekday::Sun);
+                let thanksgiving = weekday_of_month(date.year(), 11, Weekday::Thu, 4);
+                let black_friday = thanksgiving.and_then(|day| day.succ_opt()) == Some(date);
+                let christmas_eve = month == 12 && day == 24;
+                if independence_day_eve || black_friday || christmas_eve {
+                    Some(time(13, 0))
+                } else {
+                    None
+                }
+            }
+            Exchange::Lse if month == 12 && (day == 24 || day == 31) => Some(time(12, 30)),
+            _ => None,
+        }
+    }
+
+    /// Holidays in `year`, including the weekdays holidays falling on weekends are
+    /// observed on.
+    fn holidays(self, year: i32) -> Vec<NaiveDate> {
+        let date = |month, day| NaiveDate::from_ymd_opt(year, month, day);
+        let easter = easter(year);
+        let good_friday = easter.and_then(|easter| easter.checked_sub_signed(Duration::days(2)));
+        let easter_monday = easter.and_then(|easter| easter.succ_opt());
+        let holidays = match self {
+            Exchange::Nyse => {
+                let new_year = date(1, 1).filter(|day| day.weekday() != Weekday::Sat);
+                vec![
+                    new_year.and_then(observed),
+                    weekday_of_month(year, 1, Weekday::Mon, 3).filter(|_| year >= 1998),
+                    weekday_of_month(year, 2, Weekday::Mon, 3),
+                    good_friday,
+                    last_weekday_of_month(year, 5, Weekday::Mon),
+                    date(6, 19).filter(|_| year >= 2022).and_then(observed),
+                    date(7, 4).and_then(observed),
+                    weekday_of_month(year, 9, Weekday::Mon, 1),
+                    weekday_of_month(year, 11, Weekday::Thu, 4),
+                    date(12, 25).and_then(observed),
+                ]
+            }
+            Exchange::Lse => {
+                let (christmas, boxing_day) = match date(12, 25).map(|day| day.weekday()) {
+                    Some(Weekday::Sat) => (date(12, 27), date(12, 28)),
+                    Some(Weekday::Sun) => (date(12, 27), date(12, 26)),
+                    Some(Weekday::Fri) => (date(12, 25), date(12, 28)),
+                    _ => (date(12, 25), date(12, 26)),
+                };
+                vec![
+                    date(1, 1).and_then(next_monday),
+                    good_friday,
+                    easter_monday,
+                    weekday_of_month(year, 5, Weekday::Mon, 1),
+                    last_weekday_of_month(year, 5, Weekday::Mon),
+                    last_weekday_of_month(year, 8, Weekday::Mon),
+                    christmas,
+                    boxing_day,
+                ]
+            }
+            Exchange::Xetra | Exchange::Frankfurt => vec![
+                date(1, 1),
+                good_friday,
+                easter_monday,
+                date(5, 1),
+                date(12, 24),
+                date(12, 25),
+                date(12, 26),
+                date(12, 31),
+            ],
+        };
+        holidays.into_iter().flatten().collect()
+    }
+}
+
+impl TradingCalendar for Exchange {
+    fn time_zone(&self) -> Tz {
+        match self {
+            Exchange::Nyse => chrono_tz::America::New_York,
+            Exchange::Lse => chrono_tz::Europe::London,
+            Exchange::Xetra | Exchange::Frankfurt => chrono_tz::Europe::Berlin,
+        }
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        is_weekday(date) && !self.holidays(date.year()).contains(&date)
+    }
+
+    fn session_bounds(&self, date: NaiveDate) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let (open, close) = self.hours();
+        let close = self.early_close(date).unwrap_or(close);
+        Some((
+            local(self.time_zone(), date, open)?,
+            local(self.time_zone(), date, close)?,
+        ))
+    }
+}
+
+/// A calendar for a market open at the same hours every weekday, without holidays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weekdays {
+    time_zone: Tz,
+    open: NaiveTime,
+    close: NaiveTime,
+}
+
+impl Weekdays {
+    /// Create a calendar for a market open from `open` to `close` in `time_zone` every
+    /// weekday.
+    pub fn new(time_zone: Tz, open: NaiveTime, close: NaiveTime) -> Weekdays {
+        Weekdays {
+            time_zone,
+            open,
+            close,
+        }
+    }
+}
+
+impl TradingCalendar for Weekdays {
+    fn time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        is_weekday(date)
+    }
+
+    fn session_bounds(&self, date: NaiveDate) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        Some((
+            local(self.time_zone, date, self.open)?,
+            local(self.time_zone, date, self.close)?,
+        ))
+    }
+}
+
+/// Date and time in `time_zone`, or `None` if it does not exist.
+pub(crate) fn local(time_zone: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Tz>> {
+    time_zone
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+}
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn is_weekday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Weekday a holiday is observed on when it falls on a weekend: the previous Friday
+/// for Saturdays and the next Monday for Sundays.
+fn observed(date: NaiveDate) -> Option<NaiveDate> {
+    match date.weekday() {
+        Weekday::Sat => date.pred_opt(),
+        Weekday::Sun => date.succ_opt(),
+        _ => Some(date),
+    }
+}
+
+/// Weekday a holiday is observed on when it falls on a weekend: the next Monday.
+fn next_monday(date: NaiveDate) -> Option<NaiveDate> {
+    match date.weekday() {
+        Weekday::Sat => date.checked_add_signed(Duration::days(2)),
+        Weekday::Sun => date.succ_opt(),
+        _ => Some(date),
+    }
+}
+
+fn weekday_of_month(year: i32, month: u32, weekday: Weekday, n: u8) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+}
+
+fn last_weekday_of_month(year: i32, month: u32, weekday: Weekday) -> Option<NaiveDate> {
+    weekday_of_month(year, month, weekday, 5).or_else(|| weekday_of_month(year, month, weekday, 4))
+}
+
+/// Date of Easter Sunday in the Gregorian calendar.
+fn easter(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use chrono_tz::US::Eastern;
+
+    fn day(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn bounds(calendar: &impl TradingCalendar, date: &str) -> Option<(String, String)> {
+        calendar
+            .session_bounds(day(date))
+            .map(|(open, close)| (open.to_rfc3339(), close.to_rfc3339()))
+    }
+
+    #[test]
+    fn weekdays() {
+        let calendar = Weekdays::new(Eastern, time(9, 30), time(16, 0));
+        assert!(calendar.is_trading_day(day("2018-06-01")));
+        assert!(!calendar.is_trading_day(day("2018-06-02")));
+        assert!(calendar.session_bounds(day("2018-06-02")).is_none());
+        assert_eq!(
+            bounds(&calendar, "2018-06-01"),
+            Some((
+                "2018-06-01T09:30:00-04:00".to_string(),
+                "2018-06-01T16:00:00-04:00".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn easter_sunday() {
+        assert_eq!(easter(2018), Some(day("2018-04-01")));
+        assert_eq!(easter(2019), Some(day("2019-04-21")));
+        assert_eq!(easter(2024), Some(day("2024-03-31")));
+    }
+
+    #[test]
+    fn nyse() {
+        let nyse = Exchange::Nyse;
+        for holiday in &[
+            "2018-01-01",
+            "2018-01-15",
+            "2018-02-19",
+            "2018-03-30",
+            "2018-05-28",
+            "2018-07-04",
+            "2018-09-03",
+            "2018-11-22",
+            "2018-12-25",
+            "2021-12-24",
+            "2022-06-20",
+            "2023-01-02",
+        ] {
+            assert!(!nyse.is_trading_day(day(holiday)), "{}", holiday);
+        }
+        // New Year's Day falling on a Saturday is not observed.
+        assert!(nyse.is_trading_day(day("2021-12-31")));
+        assert!(nyse.is_trading_day(day("2018-06-19")));
+
+        for early_close in &["2018-07-03", "2018-11-23", "2018-12-24"] {
+            let (_, close) = nyse.session_bounds(day(early_close)).unwrap();
+            assert_eq!(close.time(), time(13, 0), "{}", early_close);
+        }
+        assert_eq!(
+            bounds(&nyse, "2018-06-01"),
+            Some((
+                "2018-06-01T09:30:00-04:00".to_string(),
+                "2018-06-01T16:00:00-04:00".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn lse_and_xetra() {
+        let lse = Exchange::Lse;
+        for holiday in &["2018-04-02", "2018-05-07", "2018-08-27", "2020-12-28"] {
+            assert!(!lse.is_trading_day(day(holiday)), "{}", holiday);
+        }
+        assert!(lse.is_trading_day(day("2018-05-01")));
+        assert_eq!(
+            bounds(&lse, "2018-12-24"),
+            Some((
+                "2018-12-24T08:00:00+00:00".to_string(),
+                "2018-12-24T12:30:00+00:00".to_string()
+            ))
+        );
+
+        let xetra = Exchange::Xetra;
+        assert!(!xetra.is_trading_day(day("2018-05-01")));
+        assert!(!xetra.is_trading_day(day("2018-12-31")));
+        assert!(xetra.is_trading_day(day("2018-05-28")));
+        assert_eq!(
+            bounds(&xetra, "2018-06-01"),
+            Some((
+                "2018-06-01T09:00:00+02:00".to_string(),
+                "2018-06-01T17:30:00+02:00".to_string()
+            ))
+        );
+        assert_eq!(
+            bounds(&Exchange::Frankfurt, "2018-06-01"),
+            Some((
+                "2018-06-01T08:00:00+02:00".to_string(),
+                "2018-06-01T22:00:00+02:00".to_string()
+            ))
+        );
+        assert_eq!(Exchange::from_region("United States"), Some(Exchange::Nyse));
+        assert_eq!(Exchange::from_region("United Kingdom"), Some(Exchange::Lse));
+        assert_eq!(Exchange::from_region("Mars"), None);
+    }
+
+    #[test]
+    fn next_session() {
+        let nyse = Exchange::Nyse;
+        let friday = parse_date("2018-06-01 17:00:00", Eastern).unwrap();
+        let (open, _) = nyse.next_session(&friday).unwrap();
+        assert_eq!(open.to_rfc3339(), "2018-06-04T09:30:00-04:00");
+
+        let morning = parse_date("2018-06-01 08:00:00", Eastern).unwrap();
+        let (open, _) = nyse
+            .next_session(&morning.with_timezone(&chrono::Utc))
+            .unwrap();
+        assert_eq!(open.to_rfc3339(), "2018-06-01T09:30:00-04:00");
+
+        let before_memorial_day = parse_date("2018-05-25 16:00:00", Eastern).unwrap();
+        let (open, _) = nyse.next_session(&before_memorial_day).unwrap();
+        assert_eq!(open.to_rfc3339(), "2018-05-29T09:30:00-04:00");
+    }
+
+    #[test]
+    fn sessions() {
+        let nyse = Exchange::Nyse;
+        let date = |value| parse_date(value, Eastern).unwrap();
+        assert_eq!(
+            nyse.session(&date("2018-06-01 09:30:00")),
+            Some(Session::PreMarket)
+        );
+        assert_eq!(
+            nyse.session(&date("2018-06-01 09:31:00")),
+            Some(Session::Regular)
+        );
+        assert_eq!(
+            nyse.session(&date("2018-06-01 16:00:00")),
+            Some(Session::Regular)
+        );
+        assert_eq!(
+            nyse.session(&date("2018-06-01 16:01:00")),
+            Some(Session::AfterHours)
+        );
+        assert_eq!(nyse.session(&date("2018-06-02 10:00:00")), None);
+
+        let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
+        let function =
+            crate::time_series::Function::IntraDay(crate::time_series::IntradayInterval::OneMinute);
+        let time_series = crate::time_series::parser::parse(&function, data).unwrap();
+        let tagged = tag_sessions(&time_series, &nyse);
+        assert_eq!(tagged.len(), 100);
+        assert!(tagged
+            .iter()
+            .all(|(_, session)| *session == Some(Session::Regular)));
+    }
+}
@@ -1,34 +1,81 @@
-use crate::api::{APIRequest, APIRequestBuilder};
+use crate::api::APIRequestBuilder;
+use crate::cache::{Cache, CacheKey, ResponseCache, TtlPolicy};
+use crate::config::ClientConfig;
+use crate::deserialize::check_status;
 use crate::error::Error;
+use crate::exchange_rate::{self, CurrencyCode};
+use crate::poll;
+use crate::rate_limit::{Budget, RateLimit, RateLimiter};
+use crate::request::{ApiRequest, RawRequest};
+use crate::retry::RetryPolicy;
+use crate::single_flight::{SharedBody, SingleFlight};
+use crate::symbol::Symbol;
+use crate::tickers;
 use crate::time_series;
-use crate::{exchange_rate, tickers};
-use std::io::Cursor;
-use std::io::Read;
+use crate::transport::Transport;
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::Proxy;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// An asynchronous client for the Alpha Vantage API.
 pub struct Client {
     builder: APIRequestBuilder,
-    client: reqwest::Client,
+    transport: Box<dyn Transport>,
+    limiter: Option<RateLimiter>,
+    retry_policy: Option<RetryPolicy>,
+    cache: Option<ResponseCache>,
+    single_flight: Option<SingleFlight>,
 }
 
 impl Client {
     /// Create a new client using the specified API `key`.
     pub fn new(key: &str) -> Client {
-        Client {
-            builder: APIRequestBuilder::new(key),
-            client: reqwest::Client::new(),
-        }
+        Client::builder(key)
+            .build()
+            .expect("failed to create client")
+    }
+
+    /// Create a builder to configure a client using the specified API `key`.
+    pub fn builder(key: &str) -> ClientBuilder {
+        ClientBuilder::new(key)
+    }
+
+    /// Limit the rate of calls made by the client.
+    ///
+    /// Equivalent to [ClientBuilder::rate_limit], which should be preferred when
+    /// creating a client.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Client {
+        self.limiter = Some(RateLimiter::new(rate_limit));
+        self
+    }
+
+    /// Retry calls failing due to transient errors according to `retry_policy`.
+    ///
+    /// Equivalent to [ClientBuilder::retry_policy], which should be preferred when
+    /// creating a client.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Client {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Number of calls which can currently be made without waiting, if rate limited.
+    pub fn remaining_budget(&self) -> Option<Budget> {
+        self.limiter.as_ref().map(RateLimiter::remaining)
     }
 
     /// Retrieve intraday time series for the specified `symbol` updated in realtime (latest 100 data points).
     pub async fn get_time_series_intraday(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
         interval: time_series::IntradayInterval,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::IntraDay(interval),
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Compact,
         )
         .await
@@ -37,12 +84,12 @@ impl Client {
     /// Retrieve intraday time series for the specified `symbol` updated in realtime (full data set).
     pub async fn get_time_series_intraday_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
         interval: time_series::IntradayInterval,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::IntraDay(interval),
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Full,
         )
         .await
@@ -51,11 +98,11 @@ impl Client {
     /// Retrieve daily time series for the specified `symbol` (latest 100 data points).
     pub async fn get_time_series_daily(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Daily,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Compact,
         )
         .await
@@ -64,11 +111,11 @@ impl Client {
     /// Retrieve daily time series for the specified `symbol` (full data set).
     pub async fn get_time_series_daily_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Daily,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Full,
         )
         .await
@@ -77,11 +124,11 @@ impl Client {
     /// Retrieve weekly time series for the specified `symbol` (latest 100 data points).
     pub async fn get_time_series_weekly(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Weekly,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Compact,
         )
         .await
@@ -90,11 +137,11 @@ impl Client {
     /// Retrieve weekly time series for the specified `symbol` (full data set).
     pub async fn get_time_series_weekly_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Weekly,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Full,
         )
         .await
@@ -103,11 +150,11 @@ impl Client {
     /// Retrieve monthly time series for the specified `symbol` (latest 100 data points).
     pub async fn get_time_series_monthly(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Monthly,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Compact,
         )
         .await
@@ -116,11 +163,11 @@ impl Client {
     /// Retrieve monthly time series for the specified `symbol` (full data set).
     pub async fn get_time_series_monthly_full(
         &self,
-        symbol: &str,
+        symbol: impl Into<Symbol>,
     ) -> Result<time_series::TimeSeries, Error> {
         self.get_time_series(
             &time_series::Function::Monthly,
-            symbol,
+            &symbol.into(),
             time_series::OutputSize::Full,
         )
         .await
@@ -130,53 +177,521 @@ impl Client {
     /// currency specified by `to_currency_code`.
     pub async fn get_exchange_rate(
         &self,
-        from_currency_code: &str,
-        to_currency_code: &str,
+        from_currency_code: impl Into<CurrencyCode>,
+        to_currency_code: impl Into<CurrencyCode>,
     ) -> Result<exchange_rate::ExchangeRate, Error> {
-        let function = "CURRENCY_EXCHANGE_RATE";
-        let params = vec![
-            ("from_currency", from_currency_code),
-            ("to_currency", to_currency_code),
-        ];
-        let request = self.builder.create(function, &params);
-        let response = self.api_call(request).await?;
-        let result = exchange_rate::parser::parse(response)?;
-        Ok(result)
+        let (from, to) = (from_currency_code.into(), to_currency_code.into());
+        from.validate()?;
+        to.validate()?;
+        let request = exchange_rate::ExchangeRateRequest::new(from.as_str(), to.as_str());
+        self.execute(&request).await
     }
 
     /// Retrieve a list of ticker symbols that match the specified `query`.
     pub async fn get_tickers(&self, query: &str) -> Result<tickers::SearchResults, Error> {
-        let function = "SYMBOL_SEARCH";
-        let params = vec![("keywords", query)];
-        let request = self.builder.create(function, &params);
-        let response = self.api_call(request).await?;
-        let result = tickers::parser::parse(Some(query.to_string()), response)?;
-        Ok(result)
+        let request = tickers::SearchRequest::new(query);
+        self.execute(&request).await
+    }
+
+    /// Retrieve intraday time series for each of the specified `symbols` (latest 100 data
+    /// points), making up to `concurrency` calls at a time.
+    pub async fn get_time_series_intraday_many(
+        &self,
+        symbols: &[impl Into<Symbol> + Clone],
+        interval: time_series::IntradayInterval,
+        concurrency: usize,
+    ) -> HashMap<String, Result<time_series::TimeSeries, Error>> {
+        self.get_time_series_many(
+            &time_series::Function::IntraDay(interval),
+            symbols,
+            concurrency,
+        )
+        .await
+    }
+
+    /// Retrieve daily time series for each of the specified `symbols` (latest 100 data
+    /// points), making up to `concurrency` calls at a time.
+    pub async fn get_time_series_daily_many(
+        &self,
+        symbols: &[impl Into<Symbol> + Clone],
+        concurrency: usize,
+    ) -> HashMap<String, Result<time_series::TimeSeries, Error>> {
+        self.get_time_series_many(&time_series::Function::Daily, symbols, concurrency)
+            .await
+    }
+
+    /// Retrieve weekly time series for each of the specified `symbols` (latest 100 data
+    /// points), making up to `concurrency` calls at a time.
+    pub async fn get_time_series_weekly_many(
+        &self,
+        symbols: &[impl Into<Symbol> + Clone],
+        concurrency: usize,
+    ) -> HashMap<String, Result<time_series::TimeSeries, Error>> {
+        self.get_time_series_many(&time_series::Function::Weekly, symbols, concurrency)
+            .await
+    }
+
+    /// Retrieve monthly time series for each of the specified `symbols` (latest 100 data
+    /// points), making up to `concurrency` calls at a time.
+    pub async fn get_time_series_monthly_many(
+        &self,
+        symbols: &[impl Into<Symbol> + Clone],
+        concurrency: usize,
+    ) -> HashMap<String, Result<time_series::TimeSeries, Error>> {
+        self.get_time_series_many(&time_series::Function::Monthly, symbols, concurrency)
+            .await
+    }
+
+    /// Retrieve the exchange rate for each of the specified `(from, to)` currency code
+    /// `pairs`, making up to `concurrency` calls at a time.
+    pub async fn get_exchange_rate_many(
+        &self,
+        pairs: &[(
+            impl Into<CurrencyCode> + Clone,
+            impl Into<CurrencyCode> + Clone,
+        )],
+        concurrency: usize,
+    ) -> HashMap<(String, String), Result<exchange_rate::ExchangeRate, Error>> {
+        let mut results = HashMap::new();
+        let mut requests = Vec::new();
+        for (from, to) in pairs {
+            let (from, to): (CurrencyCode, CurrencyCode) = (from.clone().into(), to.clone().into());
+            let key = (from.to_string(), to.to_string());
+            match from.validate().and_then(|_| to.validate()) {
+                Ok(()) => {
+                    let request =
+                        exchange_rate::ExchangeRateRequest::new(from.as_str(), to.as_str());
+                    requests.push((key, request));
+                }
+                Err(error) => {
+                    results.insert(key, Err(error));
+                }
+            }
+        }
+        results.extend(self.execute_many(requests, concurrency).await);
+        results
+    }
+
+    /// Execute each of the specified keyed `requests`, making up to `concurrency` calls
+    /// at a time, and return the result of each request under its key.
+    pub async fn execute_many<K, R, I>(
+        &self,
+        requests: I,
+        concurrency: usize,
+    ) -> HashMap<K, Result<R::Response, Error>>
+    where
+        K: Eq + Hash,
+        R: ApiRequest,
+        I: IntoIterator<Item = (K, R)>,
+    {
+        stream::iter(requests)
+            .map(|(key, request)| async move { (key, self.execute(&request).await) })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Poll the intraday time series for the specified `symbol` every `cadence`, emitting
+    /// the entries newer than those already emitted (all entries on the first poll).
+    /// Polling slows down while rate limited.
+    pub fn poll_time_series_intraday<'a>(
+        &'a self,
+        symbol: impl Into<Symbol>,
+        interval: time_series::IntradayInterval,
+        cadence: Duration,
+    ) -> impl Stream<Item = Result<time_series::Entry, Error>> + 'a {
+        let symbol = symbol.into();
+        let fetch = move || {
+            let symbol = symbol.clone();
+            async move {
+                let time_series = self.get_time_series_intraday(symbol, interval).await?;
+                Ok(time_series.entries)
+            }
+        };
+        poll::poll_new(cadence, fetch, |entry| entry.date)
+    }
+
+    /// Poll the exchange rate from the currency specified by `from_currency_code` to the
+    /// currency specified by `to_currency_code` every `cadence`, emitting it whenever it
+    /// is refreshed. Polling slows down while rate limited.
+    pub fn poll_exchange_rate<'a>(
+        &'a self,
+        from_currency_code: impl Into<CurrencyCode>,
+        to_currency_code: impl Into<CurrencyCode>,
+        cadence: Duration,
+    ) -> impl Stream<Item = Result<exchange_rate::ExchangeRate, Error>> + 'a {
+        let (from, to) = (from_currency_code.into(), to_currency_code.into());
+        let fetch = move || {
+            let (from, to) = (from.clone(), to.clone());
+            async move {
+                let exchange_rate = self.get_exchange_rate(from, to).await?;
+                Ok(vec![exchange_rate])
+            }
+        };
+        poll::poll_new(cadence, fetch, |exchange_rate| exchange_rate.date)
+    }
+
+    /// Execute the specified `request` and parse its response.
+    pub async fn execute<R: ApiRequest>(&self, request: &R) -> Result<R::Response, Error> {
+        let function = request.function();
+        let params = request.params();
+        if let Some(cache) = &self.cache {
+            if let Some(body) = cache.get(function, &params) {
+                if let Ok(response) = request.parse(body.as_slice()) {
+                    return Ok(response);
+                }
+            }
+        }
+        let body = self.shared_call(function, &params).await?;
+        let response = request.parse(body.as_slice())?;
+        if let Some(cache) = &self.cache {
+            cache.insert(function, &params, &body);
+        }
+        Ok(response)
+    }
+
+    /// Call the specified API `function` with the given `params` and return the unparsed
+    /// JSON response.
+    pub async fn call_raw(
+        &self,
+        function: &str,
+        params: &[(&str, &str)],
+    ) -> Result<serde_json::Value, Error> {
+        self.execute(&RawRequest::new(function, params)).await
+    }
+
+    /// Call the API, sharing the unparsed response with concurrent identical calls if
+    /// single flight is enabled. Each caller parses the response itself, so that
+    /// requests with different response types can share a call.
+    async fn shared_call(&self, function: &str, params: &[(&str, &str)]) -> SharedBody {
+        let single_flight = match &self.single_flight {
+            Some(single_flight) => single_flight,
+            None => return self.fetch(function, params).await.map(Arc::new),
+        };
+        let key = CacheKey::new(function, params);
+        let call = single_flight.join(&key);
+        let body = call
+            .get_or_init(|| async { self.fetch(function, params).await.map(Arc::new) })
+            .await
+            .clone();
+        single_flight.leave(&key, &call);
+        body
+    }
+
+    async fn fetch(&self, function: &str, params: &[(&str, &str)]) -> Result<Vec<u8>, Error> {
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .api_call(function, params)
+                .await
+                .and_then(|body| check_status(&body).map(|_| body));
+            let error = match result {
+                Err(error) => error,
+                body => return body,
+            };
+            let delay = self
+                .retry_policy
+                .and_then(|policy| policy.retry_delay(attempt, &error));
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(error),
+            }
+            attempt += 1;
+        }
     }
 
-    async fn get_time_series(
+    async fn get_time_series_many(
         &self,
         function: &time_series::Function,
-        symbol: &str,
+        symbols: &[impl Into<Symbol> + Clone],
+        concurrency: usize,
+    ) -> HashMap<String, Result<time_series::TimeSeries, Error>> {
+        let mut results = HashMap::new();
+        let mut requests = Vec::new();
+        for symbol in symbols {
+            let symbol: Symbol = symbol.clone().into();
+            match symbol.validate() {
+                Ok(()) => {
+                    let request = time_series::TimeSeriesRequest::new(
+                        *function,
+                        symbol.as_str(),
+                        time_series::OutputSize::Compact,
+                    );
+                    requests.push((symbol.to_string(), request));
+                }
+                Err(error) => {
+                    results.insert(symbol.to_string(), Err(error));
+                }
+            }
+        }
+        results.extend(self.execute_many(requests, concurrency).await);
+        results
+    }
+
+    pub(crate) async fn get_time_series(
+        &self,
+        function: &time_series::Function,
+        symbol: &Symbol,
         output_size: time_series::OutputSize,
     ) -> Result<time_series::TimeSeries, Error> {
-        let mut params = vec![("symbol", symbol), ("outputsize", output_size.to_string())];
-        if let time_series::Function::IntraDay(interval) = function {
-            params.push(("interval", interval.to_string()));
+        symbol.validate()?;
+        let request = time_series::TimeSeriesRequest::new(*function, symbol.as_str(), output_size);
+        self.execute(&request).await
+    }
+
+    async fn api_call(&self, function: &str, params: &[(&str, &str)]) -> Result<Vec<u8>, Error> {
+        if let Some(limiter) = &self.limiter {
+            let wait = limiter.reserve()?;
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
         }
-        let request = self.builder.create(function.into(), &params);
-        let response = self.api_call(request).await?;
-        let result = time_series::parser::parse(function, response)?;
-        Ok(result)
+        let url = self.builder.create(function, params).url();
+        let response = self.transport.fetch(&url).await?;
+        match response.status {
+            200 => {}
+            429 => return Err(Error::TooManyRequests(response.retry_after)),
+            status => return Err(Error::ServerError(status)),
+        }
+        Ok(response.body)
     }
+}
+
+/// A builder to configure a client.
+pub struct ClientBuilder {
+    config: ClientConfig,
+    transport: Option<Box<dyn Transport>>,
+    single_flight: bool,
+}
 
-    async fn api_call(&self, request: APIRequest<'_>) -> Result<impl Read, Error> {
-        let response = self.client.execute(request.into()).await?;
-        let status = response.status();
-        if status != reqwest::StatusCode::OK {
-            return Err(Error::ServerError(status.as_u16()));
+impl ClientBuilder {
+    /// Create a builder using the specified API `key`.
+    pub fn new(key: &str) -> ClientBuilder {
+        ClientBuilder {
+            config: ClientConfig::new(key),
+            transport: None,
+            single_flight: false,
         }
-        let reader = Cursor::new(response.bytes().await?);
-        Ok(reader)
+    }
+
+    /// Send requests to `base_url` instead of the Alpha Vantage endpoint
+    /// (e.g. a caching proxy or a mock server).
+    pub fn base_url(mut self, base_url: &str) -> ClientBuilder {
+        self.config.base_url = base_url.to_string();
+        self
+    }
+
+    /// Set the `timeout` for each request.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with each request.
+    pub fn user_agent(mut self, user_agent: &str) -> ClientBuilder {
+        self.config.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Send requests through the specified `proxy`.
+    pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the headers sent with each request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+        self.config.default_headers = headers;
+        self
+    }
+
+    /// Use a preconfigured HTTP `client`. The timeout, user agent, proxy and default
+    /// headers set on the builder are ignored in that case.
+    pub fn http_client(self, client: reqwest::Client) -> ClientBuilder {
+        self.transport(client)
+    }
+
+    /// Send requests through the specified `transport`. The timeout, user agent, proxy
+    /// and default headers set on the builder are ignored in that case.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> ClientBuilder {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Limit the rate of calls made by the client. Calls exceeding the limit
+    /// are delayed until enough budget is available.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> ClientBuilder {
+        self.config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Retry calls failing due to transient errors according to `retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> ClientBuilder {
+        self.config.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Cache responses in `cache` for the time determined by `ttl_policy`.
+    pub fn cache(mut self, cache: impl Cache + 'static, ttl_policy: TtlPolicy) -> ClientBuilder {
+        self.config.cache = Some(ResponseCache::new(Box::new(cache), ttl_policy));
+        self
+    }
+
+    /// Share a single call between concurrent identical requests (same function and
+    /// parameters), so that the API is only called once. Each request parses the shared
+    /// response itself, so requests with different response types can share a call.
+    ///
+    /// Only available on the asynchronous client.
+    pub fn single_flight(mut self, enabled: bool) -> ClientBuilder {
+        self.single_flight = enabled;
+        self
+    }
+
+    /// Create the client.
+    pub fn build(self) -> Result<Client, Error> {
+        let config = self.config;
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                let mut builder =
+                    reqwest::ClientBuilder::new().default_headers(config.default_headers.clone());
+                if let Some(timeout) = config.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(user_agent) = &config.user_agent {
+                    builder = builder.user_agent(user_agent.as_str());
+                }
+                if let Some(proxy) = &config.proxy {
+                    builder = builder.proxy(proxy.clone());
+                }
+                let client = builder
+                    .build()
+                    .map_err(|e| Error::ConfigurationError(e.to_string()))?;
+                Box::new(client)
+            }
+        };
+        Ok(Client {
+            builder: config.request_builder()?,
+            transport,
+            limiter: config.limiter(),
+            retry_policy: config.retry_policy,
+            cache: config.cache,
+            single_flight: if self.single_flight {
+                Some(SingleFlight::new())
+            } else {
+                None
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpResponse, MockTransport};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn batch() {
+        let transport = MockTransport::new().with_response(
+            "TIME_SERIES_DAILY",
+            &[("symbol", "MSFT"), ("outputsize", "compact")],
+            HttpResponse::ok(&include_bytes!("../tests/json/time_series_daily.json")[..]),
+        );
+        let client = Client::builder("secret")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let results = client
+            .get_time_series_daily_many(&["MSFT", "INVALID", "MS FT"], 2)
+            .await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results["MSFT"].as_ref().unwrap().entries.len(), 100);
+        assert!(matches!(results["INVALID"], Err(Error::ServerError(404))));
+        assert!(matches!(results["MS FT"], Err(Error::InvalidSymbol(_))));
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments() {
+        let client = Client::builder("secret")
+            .transport(MockTransport::new())
+            .build()
+            .unwrap();
+        let result = client.get_time_series_daily("TSCO.XYZ").await;
+        assert!(matches!(result, Err(Error::InvalidSymbol(_))));
+        let result = client.get_exchange_rate("USD", "EURO").await;
+        assert!(matches!(result, Err(Error::InvalidCurrencyCode(_))));
+        let result = client
+            .get_exchange_rate("USD", CurrencyCode::unchecked("PEPE"))
+            .await;
+        assert!(matches!(result, Err(Error::ServerError(404))));
+        let results = client
+            .get_exchange_rate_many(&[("usd", "eur"), ("USD", "XYZ")], 2)
+            .await;
+        assert!(matches!(
+            results[&("USD".to_string(), "EUR".to_string())],
+            Err(Error::ServerError(404))
+        ));
+        assert!(matches!(
+            results[&("USD".to_string(), "XYZ".to_string())],
+            Err(Error::InvalidCurrencyCode(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn too_many_requests() {
+        let transport = MockTransport::new().with_response(
+            "TIME_SERIES_DAILY",
+            &[("symbol", "MSFT"), ("outputsize", "compact")],
+            HttpResponse::new(429, "").with_retry_after(Duration::from_secs(60)),
+        );
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_secs(1));
+        let client = Client::builder("secret")
+            .transport(transport)
+            .retry_policy(policy)
+            .build()
+            .unwrap();
+        let result = client.get_time_series_daily("MSFT").await;
+        assert!(matches!(
+            result,
+            Err(Error::TooManyRequests(Some(delay))) if delay.as_secs() == 60
+        ));
+    }
+
+    #[tokio::test]
+    async fn exhausted_quota_is_not_retried() {
+        let policy = RetryPolicy::new().backoff(Duration::from_secs(5), Duration::from_secs(5));
+        let client = Client::builder("secret")
+            .transport(MockTransport::new())
+            .rate_limit(RateLimit::new().per_day(1))
+            .retry_policy(policy)
+            .build()
+            .unwrap();
+        let result = client.get_time_series_daily("MSFT").await;
+        assert!(matches!(result, Err(Error::ServerError(404))));
+        let start = std::time::Instant::now();
+        let result = client.get_time_series_daily("MSFT").await;
+        assert!(matches!(result, Err(Error::QuotaExhausted)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn with_methods() {
+        let client = Client::builder("secret")
+            .transport(MockTransport::new())
+            .build()
+            .unwrap();
+        assert!(client.remaining_budget().is_none());
+        let client = client
+            .with_rate_limit(RateLimit::new().per_minute(5))
+            .with_retry_policy(RetryPolicy::new());
+        assert_eq!(client.remaining_budget().unwrap().per_minute, Some(5));
+        assert!(client.retry_policy.is_some());
+    }
+
+    #[test]
+    fn invalid_base_url() {
+        let result = Client::builder("secret").base_url("not a url").build();
+        assert!(matches!(result, Err(Error::ConfigurationError(_))));
     }
 }
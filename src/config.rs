@@ -0,0 +1,48 @@
+use crate::api::{APIRequestBuilder, URL_ENDPOINT};
+use crate::cache::ResponseCache;
+use crate::error::Error;
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::retry::RetryPolicy;
+use reqwest::header::HeaderMap;
+use reqwest::{Proxy, Url};
+use std::time::Duration;
+
+/// Settings shared by the asynchronous and blocking client builders.
+pub(crate) struct ClientConfig {
+    pub(crate) key: String,
+    pub(crate) base_url: String,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) proxy: Option<Proxy>,
+    pub(crate) default_headers: HeaderMap,
+    pub(crate) rate_limit: Option<RateLimit>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) cache: Option<ResponseCache>,
+}
+
+impl ClientConfig {
+    pub(crate) fn new(key: &str) -> ClientConfig {
+        ClientConfig {
+            key: key.to_string(),
+            base_url: URL_ENDPOINT.to_string(),
+            timeout: None,
+            user_agent: None,
+            proxy: None,
+            default_headers: HeaderMap::new(),
+            rate_limit: None,
+            retry_policy: None,
+            cache: None,
+        }
+    }
+
+    pub(crate) fn request_builder(&self) -> Result<APIRequestBuilder, Error> {
+        let base_url = Url::parse(&self.base_url).map_err(|e| {
+            Error::ConfigurationError(format!("invalid base URL {}: {}", self.base_url, e))
+        })?;
+        Ok(APIRequestBuilder::new(&self.key, base_url))
+    }
+
+    pub(crate) fn limiter(&self) -> Option<RateLimiter> {
+        self.rate_limit.map(RateLimiter::new)
+    }
+}
@@ -0,0 +1,188 @@
+//! Currency conversion of time series using exchange rates.
+use crate::exchange_rate::{Currency, ExchangeRate};
+use crate::time_series::{Entry, TimeSeries};
+
+/// Represents a time series whose prices were converted into another currency.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConvertedTimeSeries {
+    /// Currency the prices are expressed in.
+    pub currency: Currency,
+    /// Time series with converted prices. Volumes are left unchanged.
+    pub time_series: TimeSeries,
+}
+
+impl TimeSeries {
+    /// Convert prices expressed in the `from` currency of `exchange_rate` into its `to`
+    /// currency, applying the same rate to every entry.
+    pub fn convert(&self, exchange_rate: &ExchangeRate) -> ConvertedTimeSeries {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| convert(entry, exchange_rate))
+            .collect();
+        self.converted(exchange_rate, entries)
+    }
+
+    /// Convert prices expressed in the `from` currency of `exchange_rates` into their
+    /// `to` currency, applying to each entry the latest exchange rate dated at or
+    /// before it.
+    ///
+    /// Returns `None` if `exchange_rates` is empty, mixes currency pairs, or has no
+    /// exchange rate dated at or before an entry.
+    pub fn convert_dated(&self, exchange_rates: &[ExchangeRate]) -> Option<ConvertedTimeSeries> {
+        let first = exchange_rates.first()?;
+        let same_pair = |rate: &ExchangeRate| {
+            rate.from.code == first.from.code && rate.to.code == first.to.code
+        };
+        if !exchange_rates.iter().all(same_pair) {
+            return None;
+        }
+        let mut rates: Vec<&ExchangeRate> = exchange_rates.iter().collect();
+        rates.sort_by_key(|rate| rate.date);
+
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let index = rates.partition_point(|rate| rate.date <= entry.date);
+            let rate = rates[index.checked_sub(1)?];
+            entries.push(convert(entry, rate));
+        }
+        Some(self.converted(first, entries))
+    }
+
+    fn converted(&self, exchange_rate: &ExchangeRate, entries: Vec<Entry>) -> ConvertedTimeSeries {
+        ConvertedTimeSeries {
+            currency: exchange_rate.to.clone(),
+            time_series: TimeSeries {
+                symbol: self.symbol.clone(),
+                last_refreshed: self.last_refreshed,
+                entries,
+            },
+        }
+    }
+}
+
+fn convert(entry: &Entry, exchange_rate: &ExchangeRate) -> Entry {
+    let rate = exchange_rate.rate;
+    Entry {
+        date: entry.date,
+        open: entry.open * rate,
+        high: entry.high * rate,
+        low: entry.low * rate,
+        close: entry.close * rate,
+        volume: entry.volume,
+        #[cfg(feature = "decimal")]
+        decimals: entry
+            .decimals
+            .zip(exchange_rate.rate_decimal)
+            .and_then(|(decimals, rate)| decimals.checked_mul(rate)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use chrono_tz::US::Eastern;
+
+    fn currency(code: &str) -> Currency {
+        Currency {
+            name: code.to_string(),
+            code: code.to_string(),
+        }
+    }
+
+    fn exchange_rate(date: &str, from: &str, to: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            from: currency(from),
+            to: currency(to),
+            rate,
+            date: parse_date(date, Eastern).unwrap(),
+            #[cfg(feature = "decimal")]
+            rate_decimal: Some(decimal(rate)),
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    fn decimal(value: f64) -> rust_decimal::Decimal {
+        value.to_string().parse().unwrap()
+    }
+
+    fn series() -> TimeSeries {
+        let entry = |date: &str, close: f64| Entry {
+            date: parse_date(date, Eastern).unwrap(),
+            open: 2.0,
+            high: 4.0,
+            low: 1.0,
+            close,
+            volume: 1000,
+            #[cfg(feature = "decimal")]
+            decimals: Some(crate::time_series::Decimals {
+                open: decimal(2.0),
+                high: decimal(4.0),
+                low: decimal(1.0),
+                close: decimal(close),
+            }),
+        };
+        TimeSeries {
+            symbol: "MSFT".to_string(),
+            last_refreshed: parse_date("2018-06-05", Eastern).unwrap(),
+            entries: vec![
+                entry("2018-06-01", 2.0),
+                entry("2018-06-04", 3.0),
+                entry("2018-06-05", 4.0),
+            ],
+        }
+    }
+
+    fn closes(converted: &ConvertedTimeSeries) -> Vec<f64> {
+        converted
+            .time_series
+            .entries
+            .iter()
+            .map(|entry| entry.close)
+            .collect()
+    }
+
+    #[test]
+    fn single_rate() {
+        let time_series = series();
+        let converted = time_series.convert(&exchange_rate("2018-06-05", "USD", "EUR", 0.5));
+        assert_eq!(converted.currency, currency("EUR"));
+        assert_eq!(converted.time_series.symbol, "MSFT");
+        assert_eq!(
+            converted.time_series.last_refreshed,
+            time_series.last_refreshed
+        );
+        assert_eq!(closes(&converted), vec![1.0, 1.5, 2.0]);
+        let entry = &converted.time_series.entries[0];
+        assert_eq!(entry.open, 1.0);
+        assert_eq!(entry.high, 2.0);
+        assert_eq!(entry.low, 0.5);
+        assert_eq!(entry.volume, 1000);
+        #[cfg(feature = "decimal")]
+        assert_eq!(entry.close_decimal(), Some(decimal(1.0)));
+        #[cfg(feature = "decimal")]
+        assert_eq!(entry.low_decimal(), Some(decimal(0.5)));
+    }
+
+    #[test]
+    fn dated_rates() {
+        let time_series = series();
+        let rates = vec![
+            exchange_rate("2018-06-04", "USD", "EUR", 0.25),
+            exchange_rate("2018-05-31", "USD", "EUR", 0.5),
+        ];
+        let converted = time_series.convert_dated(&rates).unwrap();
+        assert_eq!(converted.currency, currency("EUR"));
+        assert_eq!(closes(&converted), vec![1.0, 0.75, 1.0]);
+
+        assert!(time_series.convert_dated(&rates[..1]).is_none());
+        assert!(time_series.convert_dated(&[]).is_none());
+        let mixed = vec![
+            exchange_rate("2018-05-31", "USD", "EUR", 0.5),
+            exchange_rate("2018-06-01", "USD", "GBP", 0.4),
+        ];
+        assert!(time_series.convert_dated(&mixed).is_none());
+    }
+}
@@ -0,0 +1,140 @@
+//! Conversion of time series to and from [Polars](https://pola.rs) data frames,
+//! available through the optional `polars` feature.
+//!
+//! A data frame holds one row per entry with the following columns:
+//! - `date`: timezone-aware datetime, in milliseconds
+//! - `open`, `high`, `low`, `close`: `f64`
+//! - `volume`: `u64`
+use crate::time_series::{Entry, TimeSeries};
+use chrono::TimeZone as _;
+use chrono_tz::Tz;
+use polars::prelude::*;
+
+impl TimeSeries {
+    /// Convert the entries of the time series into a data frame.
+    pub fn to_dataframe(&self) -> PolarsResult<DataFrame> {
+        let time_zone = self.last_refreshed.timezone();
+        let dates: Int64Chunked = self
+            .entries
+            .iter()
+            .map(|entry| entry.date.timestamp_millis())
+            .collect_ca(PlSmallStr::from_static("date"));
+        let dates = dates.into_datetime(
+            TimeUnit::Milliseconds,
+            TimeZone::opt_try_new(Some(time_zone.name()))?,
+        );
+        let prices = |name: &'static str, value: fn(&Entry) -> f64| {
+            let values: Vec<f64> = self.entries.iter().map(value).collect();
+            Column::new(PlSmallStr::from_static(name), values)
+        };
+        let volumes: Vec<u64> = self.entries.iter().map(|entry| entry.volume).collect();
+        DataFrame::new(vec![
+            dates.into_column(),
+            prices("open", |entry| entry.open),
+            prices("high", |entry| entry.high),
+            prices("low", |entry| entry.low),
+            prices("close", |entry| entry.close),
+            Column::new(PlSmallStr::from_static("volume"), volumes),
+        ])
+    }
+
+    /// Create a time series for `symbol` from a data frame with the columns produced by
+    /// [TimeSeries::to_dataframe].
+    ///
+    /// Rows are sorted by ascending date and the date of the last row is used as the
+    /// last refreshed date. Dates without a time zone are assumed to be in UTC. Prices
+    /// have no exact decimal values, since data frames hold them as `f64`.
+    pub fn from_dataframe(symbol: &str, df: &DataFrame) -> PolarsResult<TimeSeries> {
+        let dates = df.column("date")?.datetime()?;
+        let time_zone: Tz = match dates.time_zone() {
+            Some(time_zone) => time_zone
+                .parse()
+                .map_err(|e| polars_err!(ComputeError: "invalid time zone {}: {}", time_zone, e))?,
+            None => Tz::UTC,
+        };
+        let open = df.column("open")?.cast(&DataType::Float64)?;
+        let high = df.column("high")?.cast(&DataType::Float64)?;
+        let low = df.column("low")?.cast(&DataType::Float64)?;
+        let close = df.column("close")?.cast(&DataType::Float64)?;
+        let volume = df.column("volume")?.cast(&DataType::UInt64)?;
+
+        let mut entries = dates
+            .as_datetime_iter()
+            .zip(open.f64()?)
+            .zip(high.f64()?)
+            .zip(low.f64()?)
+            .zip(close.f64()?)
+            .zip(volume.u64()?)
+            .enumerate()
+            .map(|(row, (((((date, open), high), low), close), volume))| {
+                let missing =
+                    || polars_err!(ComputeError: "missing or invalid value in row {}", row);
+                let price = |value: Option<f64>| {
+                    value.filter(|value| value.is_finite()).ok_or_else(missing)
+                };
+                Ok(Entry {
+                    date: time_zone.from_utc_datetime(&date.ok_or_else(missing)?),
+                    open: price(open)?,
+                    high: price(high)?,
+                    low: price(low)?,
+                    close: price(close)?,
+                    volume: volume.ok_or_else(missing)?,
+                    #[cfg(feature = "decimal")]
+                    decimals: None,
+                })
+            })
+            .collect::<PolarsResult<Vec<Entry>>>()?;
+        entries.sort_by_key(|entry| entry.date);
+
+        let last_refreshed = entries
+            .last()
+            .map(|entry| entry.date)
+            .ok_or_else(|| polars_err!(NoData: "empty data frame"))?;
+        Ok(TimeSeries {
+            symbol: symbol.to_string(),
+            last_refreshed,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_series::{parser, Function, IntradayInterval};
+
+    fn time_series() -> TimeSeries {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
+        parser::parse(&Function::IntraDay(IntradayInterval::OneMinute), data).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let time_series = time_series();
+        let df = time_series.to_dataframe().unwrap();
+        assert_eq!(df.height(), time_series.entries.len());
+        assert_eq!(
+            df.column("date").unwrap().dtype(),
+            &DataType::Datetime(
+                TimeUnit::Milliseconds,
+                TimeZone::opt_try_new(Some("US/Eastern")).unwrap()
+            )
+        );
+        let converted = TimeSeries::from_dataframe(&time_series.symbol, &df).unwrap();
+        #[cfg(feature = "decimal")]
+        let time_series = {
+            let mut time_series = time_series;
+            for entry in &mut time_series.entries {
+                entry.decimals = None;
+            }
+            time_series
+        };
+        assert_eq!(converted, time_series);
+    }
+
+    #[test]
+    fn missing_column() {
+        let df = time_series().to_dataframe().unwrap().drop("close").unwrap();
+        assert!(TimeSeries::from_dataframe("MSFT", &df).is_err());
+    }
+}
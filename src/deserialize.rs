@@ -1,14 +1,78 @@
 use crate::error::Error;
 use chrono::prelude::*;
 use chrono_tz::Tz;
-use serde::de::{self, Deserialize, Deserializer};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
 use std::fmt::Display;
+use std::num::ParseFloatError;
 use std::str::FromStr;
 
 pub(crate) const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 pub(crate) const DATE_FORMAT: &str = "%Y-%m-%d";
 pub(crate) const TIME_FORMAT: &str = "%H:%M";
 
+/// Fields the API uses to report errors and usage notices instead of data.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ResponseStatus {
+    #[serde(rename = "Error Message")]
+    error: Option<String>,
+    #[serde(rename = "Note")]
+    note: Option<String>,
+    #[serde(rename = "Information")]
+    information: Option<String>,
+}
+
+impl ResponseStatus {
+    pub(crate) fn check(self) -> Result<(), Error> {
+        if let Some(error) = self.error {
+            return Err(Error::APIError(error));
+        }
+        if let Some(note) = self.note {
+            return Err(Error::RateLimited(note));
+        }
+        if let Some(information) = self.information {
+            let lowercase = information.to_lowercase();
+            if lowercase.contains("premium endpoint") {
+                return Err(Error::PremiumEndpoint(information));
+            }
+            if lowercase.contains("rate limit") || lowercase.contains("call frequency") {
+                return Err(Error::RateLimited(information));
+            }
+            return Err(Error::APIError(information));
+        }
+        Ok(())
+    }
+}
+
+/// Check the status of an unparsed response, if it is a JSON object.
+pub(crate) fn check_status(body: &[u8]) -> Result<(), Error> {
+    match serde_json::from_slice::<ResponseStatus>(body) {
+        Ok(status) => status.check(),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Price parsed from a string returned by the API, along with its exact decimal value.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Price {
+    pub(crate) value: f64,
+    /// `None` if the price cannot be represented as a decimal.
+    #[cfg(feature = "decimal")]
+    pub(crate) decimal: Option<rust_decimal::Decimal>,
+}
+
+impl FromStr for Price {
+    type Err = ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Price {
+            value: s.parse()?,
+            #[cfg(feature = "decimal")]
+            decimal: s.parse().ok(),
+        })
+    }
+}
+
 pub(crate) fn from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
 where
     T: FromStr,
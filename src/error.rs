@@ -1,14 +1,35 @@
+use std::time::Duration;
+
 /// Set of errors which can occur when calling the API.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     /// Error establishing a network connection.
     ConnectionError(String),
     /// HTTP error returned by the API.
     ServerError(u16),
+    /// HTTP 429 (too many requests) returned by the API, with the delay requested by
+    /// its `Retry-After` header, if any.
+    TooManyRequests(Option<Duration>),
     /// Error parsing the API response.
     ParsingError(String),
     /// Error returned by the API.
     APIError(String),
+    /// The API call frequency or daily quota for the API key was exceeded.
+    RateLimited(String),
+    /// The daily quota of the rate limit set on the client was spent.
+    QuotaExhausted,
+    /// The API function requires a premium API key.
+    PremiumEndpoint(String),
+    /// Invalid client configuration.
+    ConfigurationError(String),
+    /// Error reading from or writing to a local store.
+    StorageError(String),
+    /// No response was recorded for a replayed request.
+    NoRecordedResponse(String),
+    /// Invalid ticker symbol.
+    InvalidSymbol(String),
+    /// Invalid currency code.
+    InvalidCurrencyCode(String),
 }
 
 impl std::fmt::Display for Error {
@@ -16,8 +37,20 @@ impl std::fmt::Display for Error {
         match self {
             Error::ConnectionError(e) => write!(f, "connection error: {}", e),
             Error::ServerError(e) => write!(f, "server returned HTTP status code {}", e),
+            Error::TooManyRequests(Some(delay)) => {
+                write!(f, "too many requests, retry after {}s", delay.as_secs())
+            }
+            Error::TooManyRequests(None) => write!(f, "too many requests"),
             Error::ParsingError(e) => write!(f, "parsing error: {}", e),
             Error::APIError(e) => write!(f, "API error: {}", e),
+            Error::RateLimited(e) => write!(f, "rate limited: {}", e),
+            Error::QuotaExhausted => write!(f, "daily quota exhausted"),
+            Error::PremiumEndpoint(e) => write!(f, "premium endpoint: {}", e),
+            Error::ConfigurationError(e) => write!(f, "configuration error: {}", e),
+            Error::StorageError(e) => write!(f, "storage error: {}", e),
+            Error::NoRecordedResponse(e) => write!(f, "no recorded response for {}", e),
+            Error::InvalidSymbol(e) => write!(f, "invalid symbol: {}", e),
+            Error::InvalidCurrencyCode(e) => write!(f, "invalid currency code: {}", e),
         }
     }
 }
@@ -41,3 +74,10 @@ impl From<chrono::format::ParseError> for Error {
         Error::ParsingError(inner.to_string())
     }
 }
+
+#[cfg(feature = "store")]
+impl From<rusqlite::Error> for Error {
+    fn from(inner: rusqlite::Error) -> Error {
+        Error::StorageError(inner.to_string())
+    }
+}
@@ -1,10 +1,43 @@
 //! Exchange rate related operations
+use crate::error::Error;
+use crate::request::ApiRequest;
 use chrono::prelude::*;
 use chrono_tz::Tz;
 use serde::Deserialize;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::str::FromStr;
+
+/// Active ISO 4217 currency codes, sorted.
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+    "CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+    "CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+    "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+    "INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+    "KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+    "MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+    "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+    "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+    "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+    "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+    "VND", "VUV", "WST", "XAF", "XAG", "XAU", "XBA", "XBB", "XBC", "XBD", "XCD", "XCG", "XDR",
+    "XOF", "XPD", "XPF", "XPT", "XSU", "XUA", "YER", "ZAR", "ZMW", "ZWG", "ZWL",
+];
+
+/// Codes of well known cryptocurrencies, sorted.
+const CRYPTOCURRENCIES: &[&str] = &[
+    "AAVE", "ADA", "ALGO", "ATOM", "AVAX", "BCH", "BNB", "BTC", "DAI", "DASH", "DOGE", "DOT",
+    "EOS", "ETC", "ETH", "FIL", "LINK", "LTC", "MATIC", "NEO", "SHIB", "SOL", "TRX", "UNI", "USDC",
+    "USDT", "XLM", "XMR", "XRP", "XTZ", "ZEC",
+];
 
 /// Represents a currency.
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Currency {
     /// The currency's name.
     pub name: String,
@@ -12,8 +45,134 @@ pub struct Currency {
     pub code: String,
 }
 
+/// Represents a currency code, either a physical currency using ISO 4217 or a
+/// cryptocurrency, such as `EUR` or `BTC`.
+///
+/// Currency codes are converted to uppercase. Parsing a currency code validates it,
+/// while converting one from a string does not: use [CurrencyCode::validate] to check
+/// it. The clients validate currency codes before calling the API, so cryptocurrencies
+/// which are not known to this crate must be created with [CurrencyCode::unchecked].
+#[derive(Debug, Clone)]
+pub struct CurrencyCode {
+    code: String,
+    unchecked: bool,
+}
+
+impl CurrencyCode {
+    /// Create a currency code which is not validated, such as a cryptocurrency which
+    /// is not known to this crate.
+    pub fn unchecked(code: &str) -> CurrencyCode {
+        CurrencyCode {
+            unchecked: true,
+            ..CurrencyCode::from(code)
+        }
+    }
+
+    /// Return the currency code as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    /// Return whether the currency code is a known cryptocurrency.
+    pub fn is_cryptocurrency(&self) -> bool {
+        CRYPTOCURRENCIES.binary_search(&self.as_str()).is_ok()
+    }
+
+    /// Return whether the currency code is an ISO 4217 code or a known cryptocurrency.
+    pub fn is_valid(&self) -> bool {
+        ISO_4217.binary_search(&self.as_str()).is_ok() || self.is_cryptocurrency()
+    }
+
+    /// Return an [Error::InvalidCurrencyCode] if the currency code is not valid, unless
+    /// it was created with [CurrencyCode::unchecked].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.unchecked || self.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::InvalidCurrencyCode(self.code.clone()))
+        }
+    }
+}
+
+impl PartialEq for CurrencyCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for CurrencyCode {}
+
+impl Hash for CurrencyCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+    }
+}
+
+impl PartialOrd for CurrencyCode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CurrencyCode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.code.cmp(&other.code)
+    }
+}
+
+impl FromStr for CurrencyCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = CurrencyCode::from(s);
+        code.validate()?;
+        Ok(code)
+    }
+}
+
+impl From<&str> for CurrencyCode {
+    fn from(code: &str) -> Self {
+        CurrencyCode::from(code.to_string())
+    }
+}
+
+impl From<&String> for CurrencyCode {
+    fn from(code: &String) -> Self {
+        CurrencyCode::from(code.as_str())
+    }
+}
+
+impl From<String> for CurrencyCode {
+    fn from(mut code: String) -> Self {
+        code.make_ascii_uppercase();
+        CurrencyCode {
+            code,
+            unchecked: false,
+        }
+    }
+}
+
+impl From<&CurrencyCode> for CurrencyCode {
+    fn from(code: &CurrencyCode) -> Self {
+        code.clone()
+    }
+}
+
+impl AsRef<str> for CurrencyCode {
+    fn as_ref(&self) -> &str {
+        &self.code
+    }
+}
+
+impl fmt::Display for CurrencyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.code)
+    }
+}
+
 /// Represents the exchange rate for a currency pair.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExchangeRate {
     /// Currency to get the exchange rate for.
     pub from: Currency,
@@ -22,19 +181,60 @@ pub struct ExchangeRate {
     /// Value of the exchange rate.
     pub rate: f64,
     /// Date the exchange rate corresponds to.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::date_time"))]
     pub date: DateTime<Tz>,
+    /// Value of the exchange rate as an exact decimal, if it was parsed from a rate
+    /// returned by the API.
+    #[cfg(feature = "decimal")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rate_decimal: Option<rust_decimal::Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ExchangeRateRequest {
+    from_currency_code: String,
+    to_currency_code: String,
+}
+
+impl ExchangeRateRequest {
+    pub(crate) fn new(from_currency_code: &str, to_currency_code: &str) -> Self {
+        ExchangeRateRequest {
+            from_currency_code: from_currency_code.to_string(),
+            to_currency_code: to_currency_code.to_string(),
+        }
+    }
+}
+
+impl ApiRequest for ExchangeRateRequest {
+    type Response = ExchangeRate;
+
+    fn function(&self) -> &str {
+        "CURRENCY_EXCHANGE_RATE"
+    }
+
+    fn params(&self) -> Vec<(&str, &str)> {
+        vec![
+            ("from_currency", self.from_currency_code.as_str()),
+            ("to_currency", self.to_currency_code.as_str()),
+        ]
+    }
+
+    fn parse<R: Read>(&self, reader: R) -> Result<ExchangeRate, Error> {
+        parser::parse(reader)
+    }
 }
 
 pub(crate) mod parser {
     use super::*;
-    use crate::deserialize::{from_str, parse_date};
-    use crate::error::Error;
-    use std::io::Read;
+    use crate::deserialize::{from_str, parse_date, Price, ResponseStatus};
 
     #[derive(Debug, Deserialize)]
     struct ExchangeRateHelper {
-        #[serde(rename = "Error Message")]
-        error: Option<String>,
+        #[serde(flatten)]
+        status: ResponseStatus,
         #[serde(rename = "Realtime Currency Exchange Rate")]
         data: Option<RealtimeExchangeRate>,
     }
@@ -50,7 +250,7 @@ pub(crate) mod parser {
         #[serde(rename = "4. To_Currency Name")]
         to_name: String,
         #[serde(rename = "5. Exchange Rate", deserialize_with = "from_str")]
-        rate: f64,
+        rate: Price,
         #[serde(rename = "6. Last Refreshed")]
         last_refreshed: String,
         #[serde(rename = "7. Time Zone")]
@@ -59,10 +259,7 @@ pub(crate) mod parser {
 
     pub(crate) fn parse(reader: impl Read) -> Result<ExchangeRate, Error> {
         let helper: ExchangeRateHelper = serde_json::from_reader(reader)?;
-
-        if let Some(error) = helper.error {
-            return Err(Error::APIError(error));
-        }
+        helper.status.check()?;
 
         let data = helper
             .data
@@ -84,8 +281,10 @@ pub(crate) mod parser {
                 name: data.to_name,
                 code: data.to_code,
             },
-            rate: data.rate,
+            rate: data.rate.value,
             date,
+            #[cfg(feature = "decimal")]
+            rate_decimal: data.rate.decimal,
         };
         Ok(exchange_rate)
     }
@@ -116,7 +315,65 @@ mod tests {
                 },
                 rate: 1.16665014,
                 date: parse_date("2018-06-23 10:27:49", UTC).unwrap(),
+                #[cfg(feature = "decimal")]
+                rate_decimal: "1.16665014".parse().ok(),
             }
         );
     }
+
+    #[test]
+    fn parse_rate_limited() {
+        let data: &[u8] = include_bytes!("../tests/json/rate_limit_information.json");
+        let result = parser::parse(BufReader::new(data));
+        assert!(matches!(result, Err(crate::Error::RateLimited(_))));
+    }
+
+    #[test]
+    fn currency_code() {
+        assert!(ISO_4217.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(CRYPTOCURRENCIES.windows(2).all(|pair| pair[0] < pair[1]));
+        let code: CurrencyCode = "eur".parse().unwrap();
+        assert_eq!(code.to_string(), "EUR");
+        assert!(!code.is_cryptocurrency());
+        assert!("BTC".parse::<CurrencyCode>().unwrap().is_cryptocurrency());
+        for code in &["", "EU", "EURO", "EUT", "XYZ", "PEPE"] {
+            assert!(
+                matches!(
+                    code.parse::<CurrencyCode>(),
+                    Err(Error::InvalidCurrencyCode(_))
+                ),
+                "{}",
+                code
+            );
+        }
+        let unchecked = CurrencyCode::unchecked("pepe");
+        assert_eq!(unchecked.as_str(), "PEPE");
+        assert!(!unchecked.is_valid());
+        assert!(unchecked.validate().is_ok());
+        assert_eq!(unchecked, CurrencyCode::from("PEPE"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let data: &[u8] = include_bytes!("../tests/json/currency_exchange_rate.json");
+        let exchange_rate =
+            parser::parse(BufReader::new(data)).expect("failed to parse exchange rate");
+        let json = serde_json::to_string(&exchange_rate).unwrap();
+        let deserialized: ExchangeRate = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, exchange_rate);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn parse_exact_rate() {
+        let data: &[u8] = include_bytes!("../tests/json/currency_exchange_rate_precise.json");
+        let exchange_rate =
+            parser::parse(BufReader::new(data)).expect("failed to parse exchange rate");
+        assert_eq!(
+            exchange_rate.rate_decimal.unwrap().to_string(),
+            "1034567890.12345678"
+        );
+        assert_eq!(exchange_rate.rate, 1034567890.1234568);
+    }
 }
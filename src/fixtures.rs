@@ -0,0 +1,320 @@
+//! Recording and replaying of API responses
+//!
+//! [RecordingTransport] wraps another transport and saves every response it
+//! receives as a JSON fixture in a directory, with the API key redacted.
+//! [ReplayTransport] serves the fixtures in a directory back, so regression
+//! tests can be built from real responses without accessing the network.
+use crate::error::Error;
+use crate::transport::{request_key, HttpResponse, RequestKey, ResponseFuture, Transport};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const REDACTED: &str = "REDACTED";
+
+/// Maximum length of the readable part of a file name, leaving room for the hash and
+/// extension within the usual 255 byte limit.
+const MAX_NAME_LENGTH: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    url: String,
+    function: String,
+    params: Vec<(String, String)>,
+    status: u16,
+    /// Delay requested by a `Retry-After` header, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
+    body: Body,
+}
+
+/// Response body, stored as text when it is valid UTF-8 so that it stays readable, and
+/// as raw bytes otherwise.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Body {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    pub(crate) fn new(body: &[u8]) -> Body {
+        match String::from_utf8(body.to_vec()) {
+            Ok(text) => Body::Text(text),
+            Err(e) => Body::Bytes(e.into_bytes()),
+        }
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Body::Text(text) => text.into_bytes(),
+            Body::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// A transport saving every response received from an inner transport to a directory.
+#[derive(Debug)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    dir: PathBuf,
+}
+
+impl<T> RecordingTransport<T> {
+    /// Record the responses received from `inner` to the directory `dir`,
+    /// which is created if needed.
+    pub fn new(inner: T, dir: impl AsRef<Path>) -> Result<RecordingTransport<T>, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| {
+            Error::StorageError(format!("unable to create {}: {}", dir.display(), e))
+        })?;
+        Ok(RecordingTransport { inner, dir })
+    }
+
+    fn record(&self, url: &Url, response: &HttpResponse) -> Result<(), Error> {
+        let (function, params) = request_key(url);
+        let path = self.dir.join(file_name(&function, &params));
+        let fixture = Fixture {
+            url: redact(url).to_string(),
+            function,
+            params,
+            status: response.status,
+            retry_after: response.retry_after.map(|delay| delay.as_secs()),
+            body: Body::new(&response.body),
+        };
+        let json = serde_json::to_string_pretty(&fixture)?;
+        fs::write(&path, json)
+            .map_err(|e| Error::StorageError(format!("unable to record {}: {}", path.display(), e)))
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a> {
+        Box::pin(async move {
+            let response = self.inner.fetch(url).await?;
+            self.record(url, &response)?;
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<T: crate::blocking::Transport> crate::blocking::Transport for RecordingTransport<T> {
+    fn fetch(&self, url: &Url) -> Result<HttpResponse, Error> {
+        let response = self.inner.fetch(url)?;
+        self.record(url, &response)?;
+        Ok(response)
+    }
+}
+
+/// A transport serving the responses recorded in a directory by a [RecordingTransport].
+///
+/// Requests without a recorded response fail with [Error::NoRecordedResponse].
+#[derive(Debug, Clone)]
+pub struct ReplayTransport {
+    responses: HashMap<RequestKey, HttpResponse>,
+}
+
+impl ReplayTransport {
+    /// Load the responses recorded in the directory `dir`.
+    pub fn new(dir: impl AsRef<Path>) -> Result<ReplayTransport, Error> {
+        let dir = dir.as_ref();
+        let io_error = |path: &Path, e: std::io::Error| {
+            Error::StorageError(format!("unable to read {}: {}", path.display(), e))
+        };
+        let mut responses = HashMap::new();
+        for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+            let path = entry.map_err(|e| io_error(dir, e))?.path();
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            let json = fs::read(&path).map_err(|e| io_error(&path, e))?;
+            let fixture: Fixture = serde_json::from_slice(&json)?;
+            let key = (fixture.function, fixture.params);
+            let mut response = HttpResponse::new(fixture.status, fixture.body.into_bytes());
+            if let Some(secs) = fixture.retry_after {
+                response = response.with_retry_after(Duration::from_secs(secs));
+            }
+            responses.insert(key, response);
+        }
+        Ok(ReplayTransport { responses })
+    }
+
+    fn replay(&self, url: &Url) -> Result<HttpResponse, Error> {
+        self.responses
+            .get(&request_key(url))
+            .cloned()
+            .ok_or_else(|| Error::NoRecordedResponse(redact(url).to_string()))
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a> {
+        Box::pin(async move { self.replay(url) })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Transport for ReplayTransport {
+    fn fetch(&self, url: &Url) -> Result<HttpResponse, Error> {
+        self.replay(url)
+    }
+}
+
+fn redact(url: &Url) -> Url {
+    let mut redacted = url.clone();
+    redacted
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(url.query_pairs().map(|(key, value)| {
+            if key == "apikey" {
+                (key, REDACTED.into())
+            } else {
+                (key, value)
+            }
+        }));
+    redacted
+}
+
+pub(crate) fn file_name(function: &str, params: &[(String, String)]) -> String {
+    let mut name = function.to_string();
+    for (key, value) in params {
+        name.push_str(&format!("-{}={}", key, value));
+    }
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '=' | '.' | '_' => c,
+            _ => '_',
+        })
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    // Distinguish keys which only differ by sanitized or truncated characters.
+    let hash = name_hash(function, params);
+    format!("{}-{:08x}.json", name, hash)
+}
+
+fn name_hash(function: &str, params: &[(String, String)]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let parts = std::iter::once(function).chain(
+        params
+            .iter()
+            .flat_map(|(key, value)| [key.as_str(), value.as_str()]),
+    );
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u32::from(byte);
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::Client;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("alphavantage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let dir = temp_dir("fixtures");
+        let mock = MockTransport::new().with_response(
+            "CURRENCY_EXCHANGE_RATE",
+            &[("from_currency", "EUR"), ("to_currency", "USD")],
+            HttpResponse::ok(&include_bytes!("../tests/json/currency_exchange_rate.json")[..]),
+        );
+        let recording = RecordingTransport::new(mock, &dir).unwrap();
+        let client = Client::builder("secret")
+            .transport(recording)
+            .build()
+            .unwrap();
+        let recorded = client.get_exchange_rate("EUR", "USD").await.unwrap();
+
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let path = files[0].as_ref().unwrap().path();
+        let fixture = fs::read_to_string(path).unwrap();
+        assert!(fixture.contains("apikey=REDACTED"));
+        assert!(!fixture.contains("secret"));
+
+        let replay = ReplayTransport::new(&dir).unwrap();
+        let client = Client::builder("other").transport(replay).build().unwrap();
+        let replayed = client.get_exchange_rate("EUR", "USD").await.unwrap();
+        assert_eq!(recorded, replayed);
+
+        let result = client.get_exchange_rate("USD", "EUR").await;
+        assert!(matches!(result, Err(Error::NoRecordedResponse(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn binary_bodies() {
+        let dir = temp_dir("binary-fixtures");
+        let body = vec![0x00, 0xff, 0xfe, b'{'];
+        let mock = MockTransport::new().with_response(
+            "SYMBOL_SEARCH",
+            &[("keywords", "tesco")],
+            HttpResponse::new(500, body.clone()),
+        );
+        let recording = RecordingTransport::new(mock, &dir).unwrap();
+        let url =
+            Url::parse("https://example.com/query?function=SYMBOL_SEARCH&keywords=tesco").unwrap();
+        let recorded = Transport::fetch(&recording, &url).await.unwrap();
+        assert_eq!(recorded.body, body);
+
+        let replay = ReplayTransport::new(&dir).unwrap();
+        let replayed = Transport::fetch(&replay, &url).await.unwrap();
+        assert_eq!(replayed, recorded);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let result = ReplayTransport::new(&dir);
+        assert!(matches!(result, Err(Error::StorageError(_))));
+    }
+
+    #[tokio::test]
+    async fn retry_after() {
+        let dir = temp_dir("retry-after-fixtures");
+        let mock = MockTransport::new().with_response(
+            "GLOBAL_QUOTE",
+            &[("symbol", "MSFT")],
+            HttpResponse::new(429, "").with_retry_after(Duration::from_secs(30)),
+        );
+        let recording = RecordingTransport::new(mock, &dir).unwrap();
+        let url =
+            Url::parse("https://example.com/query?function=GLOBAL_QUOTE&symbol=MSFT").unwrap();
+        let recorded = Transport::fetch(&recording, &url).await.unwrap();
+
+        let replay = ReplayTransport::new(&dir).unwrap();
+        let replayed = Transport::fetch(&replay, &url).await.unwrap();
+        assert_eq!(replayed, recorded);
+        assert_eq!(replayed.retry_after, Some(Duration::from_secs(30)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn long_file_names() {
+        let keywords = "x".repeat(1000);
+        let name = file_name("SYMBOL_SEARCH", &[("keywords".into(), keywords.clone())]);
+        assert!(name.len() <= 255);
+        assert!(name.ends_with(".json"));
+        let other = file_name("SYMBOL_SEARCH", &[("keywords".into(), keywords + "y")]);
+        assert_ne!(name, other);
+
+        let dir = temp_dir("long-fixtures");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(&name), "{}").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -11,16 +11,73 @@
 //!
 //! The default [Client] is asynchronous but a
 //! blocking client is also available through the optional `blocking` feature.
+//! Client methods take a [symbol::Symbol] or an [exchange_rate::CurrencyCode], which
+//! can be converted from strings and are validated before calling the API.
+//!
+//! Other API functions can be called by implementing [request::ApiRequest] and
+//! passing the request to [Client::execute], or by using [Client::call_raw] to
+//! retrieve the unparsed JSON response.
+//!
+//! Both clients can be configured through a [ClientBuilder] (e.g. to set a
+//! different base URL or a request timeout), including a [rate_limit::RateLimit]
+//! to stay within the quota of the API key and a [retry::RetryPolicy] to retry
+//! calls failing due to transient errors. Responses can also be cached using the
+//! [cache] module. Tests can use a
+//! [transport::MockTransport] to serve canned responses instead of calling the API,
+//! or replay responses previously recorded with the [fixtures] module.
+//!
+//! Time series can be resampled to coarser intervals with the [resample] module, and
+//! technical indicators can be computed locally with the [analytics] module, while the
+//! [statistics] module adds return, volatility and drawdown statistics. Several time
+//! series can be aligned on common dates with the [panel] module, and validated against
+//! a [calendar::TradingCalendar] with the [quality] module. The [calendar] module also
+//! provides the holidays and trading hours of major exchanges, and tags intraday
+//! entries with their trading session. Prices can be converted into another currency
+//! with the [conversion] module.
+//!
+//! The optional `serde` feature implements `Serialize` and `Deserialize` for the
+//! types returned by the clients, while the optional `decimal` feature keeps prices and
+//! exchange rates as exact decimals parsed from the API responses alongside their `f64`
+//! values (e.g. `time_series::Entry::close_decimal`). Time series can be
+//! converted to and from Polars data frames with the optional `polars` feature (see
+//! the `dataframe` module), and to Arrow record batches and Parquet files with the
+//! optional `arrow` feature (see the `record_batch` module). The optional `store`
+//! feature keeps time series in a local SQLite database (see the `store` module).
 
 mod api;
 mod client;
+mod config;
 mod deserialize;
 mod error;
+mod poll;
+#[cfg(feature = "serde")]
+mod serialize;
+mod single_flight;
 
+pub mod analytics;
 #[cfg(feature = "blocking")]
 pub mod blocking;
+pub mod cache;
+pub mod calendar;
+pub mod conversion;
+#[cfg(feature = "polars")]
+pub mod dataframe;
 pub mod exchange_rate;
+pub mod fixtures;
+pub mod panel;
+pub mod quality;
+pub mod rate_limit;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+pub mod request;
+pub mod resample;
+pub mod retry;
+pub mod statistics;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod symbol;
 pub mod tickers;
 pub mod time_series;
-pub use crate::client::Client;
+pub mod transport;
+pub use crate::client::{Client, ClientBuilder};
 pub use crate::error::Error;
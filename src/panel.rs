@@ -0,0 +1,271 @@
+//! Alignment of several time series on common dates, and merging of time series.
+use crate::time_series::{Entry, TimeSeries};
+use chrono::{DateTime, NaiveDateTime};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashSet};
+
+/// Represents the dates a panel is aligned on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alignment {
+    /// Dates of the entries of any time series.
+    Union,
+    /// Dates of the entries of all time series.
+    Intersection,
+}
+
+/// Represents how missing entries are filled in a panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fill {
+    /// Leave missing entries empty.
+    None,
+    /// Use the previous entry of the same time series, if any.
+    Forward,
+}
+
+/// Represents several time series aligned on common dates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Panel {
+    /// Symbols of the time series, in the order given.
+    pub symbols: Vec<String>,
+    /// Rows, sorted by ascending dates.
+    pub rows: Vec<Row>,
+}
+
+/// Represents the entries of each time series of a panel at a given date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// Date, in the time zone of the first time series with an entry at that date.
+    pub date: DateTime<Tz>,
+    /// Entries in the same order as the symbols of the panel. Forward filled entries
+    /// keep the date they were originally reported at.
+    pub entries: Vec<Option<Entry>>,
+}
+
+impl Panel {
+    /// Return the entries of the time series for `symbol`, one per row.
+    pub fn column(&self, symbol: &str) -> Option<Vec<Option<&Entry>>> {
+        let index = self.symbols.iter().position(|s| s == symbol)?;
+        Some(
+            self.rows
+                .iter()
+                .map(|row| row.entries[index].as_ref())
+                .collect(),
+        )
+    }
+}
+
+/// Align `time_series` on the dates given by `alignment`, filling missing entries
+/// according to `fill`.
+///
+/// Dates are compared as instants, so time series reported in different time zones
+/// are aligned correctly.
+pub fn align(time_series: &[TimeSeries], alignment: Alignment, fill: Fill) -> Panel {
+    // Number of time series with an entry at each date, ignoring duplicate entries.
+    let mut dates: BTreeMap<NaiveDateTime, (DateTime<Tz>, usize)> = BTreeMap::new();
+    for series in time_series {
+        let mut seen = HashSet::new();
+        for entry in &series.entries {
+            let instant = entry.date.naive_utc();
+            let (_, count) = dates.entry(instant).or_insert((entry.date, 0));
+            if seen.insert(instant) {
+                *count += 1;
+            }
+        }
+    }
+
+    let mut positions = vec![0; time_series.len()];
+    let mut previous: Vec<Option<&Entry>> = vec![None; time_series.len()];
+    let mut rows = Vec::new();
+    for (instant, (date, count)) in dates {
+        let mut entries = Vec::with_capacity(time_series.len());
+        for (i, series) in time_series.iter().enumerate() {
+            let remaining = &series.entries[positions[i]..];
+            let skipped = remaining.partition_point(|entry| entry.date.naive_utc() < instant);
+            positions[i] += skipped;
+            if skipped > 0 {
+                previous[i] = Some(&remaining[skipped - 1]);
+            }
+            let entry = match series.entries.get(positions[i]) {
+                Some(entry) if entry.date.naive_utc() == instant => {
+                    positions[i] += 1;
+                    previous[i] = Some(entry);
+                    Some(entry)
+                }
+                _ if fill == Fill::Forward => previous[i],
+                _ => None,
+            };
+            entries.push(entry.cloned());
+        }
+        if alignment == Alignment::Union || count == time_series.len() {
+            rows.push(Row { date, entries });
+        }
+    }
+
+    Panel {
+        symbols: time_series
+            .iter()
+            .map(|series| series.symbol.clone())
+            .collect(),
+        rows,
+    }
+}
+
+impl TimeSeries {
+    /// Merge the entries of `other`, a time series of the same symbol, into a new time
+    /// series, e.g. to update a full history with compact data.
+    ///
+    /// Where both time series have an entry at the same date, the entry of the time
+    /// series with the latest last refreshed date is kept, or the entry of `other` if
+    /// both were refreshed at the same date. Returns `None` if the symbols differ.
+    pub fn merge(&self, other: &TimeSeries) -> Option<TimeSeries> {
+        if self.symbol != other.symbol {
+            return None;
+        }
+        let (older, newer) = if other.last_refreshed >= self.last_refreshed {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut entries: BTreeMap<NaiveDateTime, &Entry> = BTreeMap::new();
+        for entry in older.entries.iter().chain(&newer.entries) {
+            entries.insert(entry.date.naive_utc(), entry);
+        }
+        Some(TimeSeries {
+            symbol: self.symbol.clone(),
+            last_refreshed: newer.last_refreshed,
+            entries: entries.into_values().cloned().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use chrono_tz::US::Eastern;
+
+    fn entry(date: &str, close: f64) -> Entry {
+        Entry {
+            date: parse_date(date, Eastern).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1000,
+            #[cfg(feature = "decimal")]
+            decimals: None,
+        }
+    }
+
+    fn series(symbol: &str, last_refreshed: &str, entries: Vec<Entry>) -> TimeSeries {
+        TimeSeries {
+            symbol: symbol.to_string(),
+            last_refreshed: parse_date(last_refreshed, Eastern).unwrap(),
+            entries,
+        }
+    }
+
+    fn closes(panel: &Panel, symbol: &str) -> Vec<Option<f64>> {
+        panel
+            .column(symbol)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.map(|entry| entry.close))
+            .collect()
+    }
+
+    fn panel_series() -> Vec<TimeSeries> {
+        vec![
+            series(
+                "MSFT",
+                "2018-06-05",
+                vec![
+                    entry("2018-06-01", 1.0),
+                    entry("2018-06-04", 2.0),
+                    entry("2018-06-05", 3.0),
+                ],
+            ),
+            series(
+                "GOOG",
+                "2018-06-06",
+                vec![entry("2018-06-04", 10.0), entry("2018-06-06", 30.0)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn union() {
+        let panel = align(&panel_series(), Alignment::Union, Fill::None);
+        assert_eq!(panel.symbols, vec!["MSFT", "GOOG"]);
+        assert_eq!(panel.rows.len(), 4);
+        assert_eq!(
+            panel.rows[3].date,
+            parse_date("2018-06-06", Eastern).unwrap()
+        );
+        assert_eq!(
+            closes(&panel, "MSFT"),
+            vec![Some(1.0), Some(2.0), Some(3.0), None]
+        );
+        assert_eq!(
+            closes(&panel, "GOOG"),
+            vec![None, Some(10.0), None, Some(30.0)]
+        );
+
+        let panel = align(&panel_series(), Alignment::Union, Fill::Forward);
+        assert_eq!(
+            closes(&panel, "MSFT"),
+            vec![Some(1.0), Some(2.0), Some(3.0), Some(3.0)]
+        );
+        assert_eq!(
+            closes(&panel, "GOOG"),
+            vec![None, Some(10.0), Some(10.0), Some(30.0)]
+        );
+        let filled = panel.column("GOOG").unwrap()[2].unwrap();
+        assert_eq!(filled.date, parse_date("2018-06-04", Eastern).unwrap());
+        assert!(panel.column("AAPL").is_none());
+    }
+
+    #[test]
+    fn intersection() {
+        let panel = align(&panel_series(), Alignment::Intersection, Fill::Forward);
+        assert_eq!(panel.rows.len(), 1);
+        assert_eq!(
+            panel.rows[0].date,
+            parse_date("2018-06-04", Eastern).unwrap()
+        );
+        assert_eq!(closes(&panel, "GOOG"), vec![Some(10.0)]);
+
+        let mut time_series = panel_series();
+        time_series[1].entries.push(entry("2018-06-06", 30.0));
+        let panel = align(&time_series, Alignment::Intersection, Fill::None);
+        assert_eq!(panel.rows.len(), 1);
+    }
+
+    #[test]
+    fn merge() {
+        let history = series(
+            "MSFT",
+            "2018-06-04",
+            vec![entry("2018-06-01", 1.0), entry("2018-06-04", 2.0)],
+        );
+        let update = series(
+            "MSFT",
+            "2018-06-05",
+            vec![entry("2018-06-04", 2.5), entry("2018-06-05", 3.0)],
+        );
+        let expected = series(
+            "MSFT",
+            "2018-06-05",
+            vec![
+                entry("2018-06-01", 1.0),
+                entry("2018-06-04", 2.5),
+                entry("2018-06-05", 3.0),
+            ],
+        );
+        assert_eq!(history.merge(&update).unwrap(), expected);
+        assert_eq!(update.merge(&history).unwrap(), expected);
+
+        let other = series("GOOG", "2018-06-05", vec![]);
+        assert!(history.merge(&other).is_none());
+    }
+}
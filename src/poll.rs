@@ -0,0 +1,125 @@
+use crate::error::Error;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use futures::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+/// Maximum number of times the polling interval is doubled while rate limited.
+const MAX_BACKOFF_EXPONENT: u32 = 5;
+
+struct State<T, F> {
+    fetch: F,
+    date: fn(&T) -> DateTime<Tz>,
+    last_seen: Option<DateTime<Tz>>,
+    pending: VecDeque<T>,
+    wait: Option<Duration>,
+    backoff: u32,
+}
+
+/// Call `fetch` every `interval` and emit the items dated after the last emitted item.
+///
+/// Items returned by `fetch` must be sorted by ascending date. All items are emitted
+/// after the first call. When rate limited, the interval is doubled on each attempt
+/// until a call succeeds. Other errors are emitted and polling continues.
+pub(crate) fn poll_new<'a, T, F, Fut>(
+    interval: Duration,
+    fetch: F,
+    date: fn(&T) -> DateTime<Tz>,
+) -> impl Stream<Item = Result<T, Error>> + 'a
+where
+    T: 'a,
+    F: FnMut() -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>, Error>> + 'a,
+{
+    let state = State {
+        fetch,
+        date,
+        last_seen: None,
+        pending: VecDeque::new(),
+        wait: None,
+        backoff: 0,
+    };
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((Ok(item), state));
+            }
+            if let Some(wait) = state.wait {
+                tokio::time::sleep(wait).await;
+            }
+            match (state.fetch)().await {
+                Ok(items) => {
+                    state.backoff = 0;
+                    state.wait = Some(interval);
+                    let date = state.date;
+                    let last_seen = state.last_seen;
+                    state.pending.extend(
+                        items
+                            .into_iter()
+                            .filter(|item| last_seen.is_none_or(|last| date(item) > last)),
+                    );
+                    if let Some(item) = state.pending.back() {
+                        state.last_seen = Some(date(item));
+                    }
+                }
+                Err(Error::RateLimited(_)) => {
+                    state.backoff = (state.backoff + 1).min(MAX_BACKOFF_EXPONENT);
+                    state.wait = Some(interval * 2u32.pow(state.backoff));
+                }
+                Err(error) => {
+                    state.wait = Some(interval);
+                    return Some((Err(error), state));
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use chrono_tz::US::Eastern;
+    use futures::StreamExt;
+    use std::sync::Mutex;
+
+    fn dates(values: &[&str]) -> Vec<DateTime<Tz>> {
+        values
+            .iter()
+            .map(|value| parse_date(value, Eastern).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn emits_new_items() {
+        let responses = Mutex::new(VecDeque::from(vec![
+            Ok(dates(&["2018-06-01 15:58:00", "2018-06-01 15:59:00"])),
+            Err(Error::RateLimited("note".into())),
+            Ok(dates(&["2018-06-01 15:59:00", "2018-06-01 16:00:00"])),
+            Err(Error::APIError("invalid".into())),
+            Ok(dates(&["2018-06-01 15:59:00", "2018-06-01 16:00:00"])),
+            Ok(dates(&["2018-06-01 16:00:00", "2018-06-01 16:01:00"])),
+        ]));
+        let fetch = || async { responses.lock().unwrap().pop_front().unwrap() };
+        let items: Vec<_> = poll_new(Duration::from_millis(1), fetch, |date| *date)
+            .take(5)
+            .collect()
+            .await;
+        assert_eq!(items.len(), 5);
+        let expected = dates(&[
+            "2018-06-01 15:58:00",
+            "2018-06-01 15:59:00",
+            "2018-06-01 16:00:00",
+        ]);
+        for (item, date) in items.iter().zip(&expected) {
+            assert_eq!(item.as_ref().unwrap(), date);
+        }
+        assert!(matches!(items[3], Err(Error::APIError(_))));
+        assert_eq!(
+            items[4].as_ref().unwrap(),
+            &parse_date("2018-06-01 16:01:00", Eastern).unwrap()
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Data quality validation of time series.
+use crate::calendar::{self, TradingCalendar};
+use crate::time_series::{Entry, Function, TimeSeries};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+
+/// Represents the data quality issues found in a time series, by entry date.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// Dates of expected entries which are missing. Missing daily, weekly and monthly
+    /// entries are dated at the last trading day of their period.
+    pub missing: Vec<DateTime<Tz>>,
+    /// Dates of entries reported more than once.
+    pub duplicates: Vec<DateTime<Tz>>,
+    /// Dates of entries with a price of zero or less.
+    pub non_positive_prices: Vec<DateTime<Tz>>,
+    /// Dates of entries with a high price lower than the low price.
+    pub high_below_low: Vec<DateTime<Tz>>,
+    /// Dates of entries with an open or close price outside of the low and high prices.
+    pub outside_range: Vec<DateTime<Tz>>,
+    /// Dates of entries without trading volume.
+    pub zero_volume: Vec<DateTime<Tz>>,
+}
+
+impl Report {
+    /// Return whether no issues were found.
+    pub fn is_clean(&self) -> bool {
+        self == &Report::default()
+    }
+}
+
+/// Validate the entries of `time_series` retrieved with `function`, using `calendar` to
+/// determine which entries are expected between the first and last entries.
+///
+/// Intraday entries are expected at each interval during the regular session, dated
+/// at the end of the interval.
+pub fn validate(
+    time_series: &TimeSeries,
+    function: &Function,
+    calendar: &impl TradingCalendar,
+) -> Report {
+    let mut report = Report {
+        missing: missing(time_series, function, calendar),
+        ..Report::default()
+    };
+    let mut dates = HashSet::new();
+    for entry in &time_series.entries {
+        if !dates.insert(entry.date.naive_utc()) {
+            report.duplicates.push(entry.date);
+        }
+        check_entry(entry, &mut report);
+    }
+    report
+}
+
+fn check_entry(entry: &Entry, report: &mut Report) {
+    let prices = [entry.open, entry.high, entry.low, entry.close];
+    if prices.iter().any(|price| *price <= 0.0) {
+        report.non_positive_prices.push(entry.date);
+    }
+    if entry.high < entry.low {
+        report.high_below_low.push(entry.date);
+    }
+    let outside = |price: f64| price < entry.low || price > entry.high;
+    if outside(entry.open) || outside(entry.close) {
+        report.outside_range.push(entry.date);
+    }
+    if entry.volume == 0 {
+        report.zero_volume.push(entry.date);
+    }
+}
+
+fn missing(
+    time_series: &TimeSeries,
+    function: &Function,
+    calendar: &impl TradingCalendar,
+) -> Vec<DateTime<Tz>> {
+    let (first, last) = match (time_series.entries.first(), time_series.entries.last()) {
+        (Some(first), Some(last)) => (first.date, last.date),
+        _ => return Vec::new(),
+    };
+    let time_zone = first.timezone();
+    let days = first
+        .date_naive()
+        .iter_days()
+        .take_while(|day| *day <= last.date_naive())
+        .filter(|day| calendar.is_trading_day(*day));
+
+    match function {
+        Function::IntraDay(interval) => {
+            let present: HashSet<NaiveDateTime> = time_series
+                .entries
+                .iter()
+                .map(|entry| entry.date.naive_utc())
+                .collect();
+            let interval = Duration::minutes(interval.minutes());
+            let mut missing = Vec::new();
+            for (open, close) in days.filter_map(|day| calendar.session_bounds(day)) {
+                let mut date = open + interval;
+                while date <= close {
+                    let expected = date >= first && date <= last;
+                    if expected && !present.contains(&date.naive_utc()) {
+                        missing.push(date.with_timezone(&time_zone));
+                    }
+                    date += interval;
+                }
+            }
+            missing
+        }
+        Function::Daily => missing_periods(time_series, days, |day| day),
+        Function::Weekly => missing_periods(time_series, days, |day| {
+            let week = day.iso_week();
+            (week.year(), week.week())
+        }),
+        Function::Monthly => missing_periods(time_series, days, |day| (day.year(), day.month())),
+    }
+}
+
+/// Dates of the last trading day of each period of `days` without entries.
+fn missing_periods<K: Eq + std::hash::Hash>(
+    time_series: &TimeSeries,
+    days: impl Iterator<Item = NaiveDate>,
+    period: impl Fn(NaiveDate) -> K,
+) -> Vec<DateTime<Tz>> {
+    let present: HashSet<K> = time_series
+        .entries
+        .iter()
+        .map(|entry| period(entry.date.date_naive()))
+        .collect();
+    let time_zone = time_series.last_refreshed.timezone();
+    let mut missing: Vec<(K, NaiveDate)> = Vec::new();
+    for day in days {
+        let key = period(day);
+        if present.contains(&key) {
+            continue;
+        }
+        match missing.last_mut() {
+            Some((last, last_day)) if *last == key => *last_day = day,
+            _ => missing.push((key, day)),
+        }
+    }
+    missing
+        .into_iter()
+        .filter_map(|(_, day)| calendar::local(time_zone, day, NaiveTime::MIN))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{Exchange, Weekdays};
+    use crate::deserialize::parse_date;
+    use crate::time_series::{parser, IntradayInterval};
+    use chrono_tz::US::Eastern;
+
+    fn calendar() -> Weekdays {
+        Weekdays::new(
+            Eastern,
+            NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn intraday() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
+        let function = Function::IntraDay(IntradayInterval::OneMinute);
+        let mut time_series = parser::parse(&function, data).unwrap();
+        assert!(validate(&time_series, &function, &calendar()).is_clean());
+
+        let removed = time_series.entries.remove(10).date;
+        let duplicate = time_series.entries[20].clone();
+        time_series.entries.insert(21, duplicate.clone());
+        time_series.entries[30].low = 0.0;
+        time_series.entries[40].high = 1.0;
+        time_series.entries[50].close = time_series.entries[50].high + 1.0;
+        time_series.entries[60].volume = 0;
+        let report = validate(&time_series, &function, &calendar());
+        assert_eq!(report.missing, vec![removed]);
+        assert_eq!(report.duplicates, vec![duplicate.date]);
+        assert_eq!(
+            report.non_positive_prices,
+            vec![time_series.entries[30].date]
+        );
+        assert_eq!(report.high_below_low, vec![time_series.entries[40].date]);
+        assert_eq!(
+            report.outside_range,
+            vec![time_series.entries[40].date, time_series.entries[50].date]
+        );
+        assert_eq!(report.zero_volume, vec![time_series.entries[60].date]);
+    }
+
+    #[test]
+    fn daily() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_daily.json");
+        let time_series = parser::parse(&Function::Daily, data).unwrap();
+        let report = validate(&time_series, &Function::Daily, &calendar());
+        // Weekday holidays are missing from a calendar without holidays.
+        assert!(report
+            .missing
+            .contains(&parse_date("2018-05-28", Eastern).unwrap()));
+        assert!(report.duplicates.is_empty());
+        assert!(report.non_positive_prices.is_empty());
+        assert!(validate(&time_series, &Function::Daily, &Exchange::Nyse).is_clean());
+
+        let weekly = time_series.resample_calendar(crate::resample::CalendarPeriod::Weekly);
+        let mut entries = weekly.entries.clone();
+        let removed = entries.remove(5);
+        let weekly = TimeSeries { entries, ..weekly };
+        let report = validate(&weekly, &Function::Weekly, &calendar());
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].iso_week(), removed.date.iso_week());
+    }
+}
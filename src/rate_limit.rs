@@ -0,0 +1,233 @@
+//! Client-side rate limiting
+//!
+//! A [RateLimit] can be set on a client so that requests exceeding the
+//! per-minute quota of the API key are delayed until enough budget is
+//! available, instead of being rejected by the API. Requests exceeding the
+//! daily quota fail immediately with [Error::QuotaExhausted] rather than
+//! waiting for hours, and are not retried.
+use crate::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Maximum number of calls allowed per minute and per day.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    per_minute: Option<u32>,
+    per_day: Option<u32>,
+}
+
+impl RateLimit {
+    /// Create a rate limit without any quotas.
+    pub fn new() -> RateLimit {
+        RateLimit::default()
+    }
+
+    /// Limit the number of calls per minute to `calls` (at least one).
+    pub fn per_minute(mut self, calls: u32) -> RateLimit {
+        self.per_minute = Some(calls.max(1));
+        self
+    }
+
+    /// Limit the number of calls per day to `calls` (at least one).
+    ///
+    /// Once the daily quota is spent, calls fail with [Error::QuotaExhausted] until it
+    /// is refilled, which happens gradually over the day.
+    pub fn per_day(mut self, calls: u32) -> RateLimit {
+        self.per_day = Some(calls.max(1));
+        self
+    }
+}
+
+/// Number of calls which can currently be made without waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// Calls remaining for the current minute, if limited.
+    pub per_minute: Option<u32>,
+    /// Calls remaining for the current day, if limited.
+    pub per_day: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+}
+
+impl Bucket {
+    fn new(calls: u32, period: Duration) -> Bucket {
+        let capacity = f64::from(calls);
+        Bucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / period.as_secs_f64(),
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+    }
+
+    fn take(&mut self) -> Duration {
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.refill_per_sec)
+        }
+    }
+
+    fn remaining(&self) -> u32 {
+        self.tokens.max(0.0).floor() as u32
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tokens < 1.0
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    updated: Instant,
+    minute: Option<Bucket>,
+    day: Option<Bucket>,
+}
+
+impl State {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = self.updated.max(now);
+        for bucket in self.buckets() {
+            bucket.refill(elapsed);
+        }
+    }
+
+    fn buckets(&mut self) -> impl Iterator<Item = &mut Bucket> {
+        self.minute.iter_mut().chain(self.day.iter_mut())
+    }
+}
+
+/// Token bucket limiter shared by all requests of a client.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            state: Mutex::new(State {
+                updated: Instant::now(),
+                minute: limit.per_minute.map(|calls| Bucket::new(calls, MINUTE)),
+                day: limit.per_day.map(|calls| Bucket::new(calls, DAY)),
+            }),
+        }
+    }
+
+    /// Reserve a call and return how long to wait before making it, or
+    /// [Error::QuotaExhausted] without reserving it if the daily quota is spent.
+    ///
+    /// Reservations are granted in order, so concurrent callers are queued.
+    pub(crate) fn reserve(&self) -> Result<Duration, Error> {
+        self.reserve_at(Instant::now())
+    }
+
+    pub(crate) fn remaining(&self) -> Budget {
+        self.remaining_at(Instant::now())
+    }
+
+    fn reserve_at(&self, now: Instant) -> Result<Duration, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.refill(now);
+        if state.day.as_ref().is_some_and(Bucket::is_empty) {
+            return Err(Error::QuotaExhausted);
+        }
+        Ok(state
+            .buckets()
+            .map(Bucket::take)
+            .max()
+            .unwrap_or(Duration::ZERO))
+    }
+
+    fn remaining_at(&self, now: Instant) -> Budget {
+        let mut state = self.state.lock().unwrap();
+        state.refill(now);
+        Budget {
+            per_minute: state.minute.as_ref().map(Bucket::remaining),
+            per_day: state.day.as_ref().map(Bucket::remaining),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited() {
+        let limiter = RateLimiter::new(RateLimit::new());
+        for _ in 0..100 {
+            assert_eq!(limiter.reserve().unwrap(), Duration::ZERO);
+        }
+        assert_eq!(
+            limiter.remaining(),
+            Budget {
+                per_minute: None,
+                per_day: None
+            }
+        );
+    }
+
+    #[test]
+    fn queues_calls_over_quota() {
+        let limiter = RateLimiter::new(RateLimit::new().per_minute(5).per_day(100));
+        let now = limiter.state.lock().unwrap().updated;
+        for _ in 0..5 {
+            assert_eq!(limiter.reserve_at(now).unwrap(), Duration::ZERO);
+        }
+        assert_eq!(
+            limiter.remaining_at(now),
+            Budget {
+                per_minute: Some(0),
+                per_day: Some(95)
+            }
+        );
+        let first = limiter.reserve_at(now).unwrap();
+        let second = limiter.reserve_at(now).unwrap();
+        assert!((first.as_secs_f64() - 12.0).abs() < 1e-6);
+        assert!((second.as_secs_f64() - 24.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(RateLimit::new().per_minute(5));
+        let now = limiter.state.lock().unwrap().updated;
+        for _ in 0..5 {
+            limiter.reserve_at(now).unwrap();
+        }
+        let later = now + Duration::from_secs(30);
+        assert_eq!(limiter.remaining_at(later).per_minute, Some(2));
+        let much_later = now + Duration::from_secs(600);
+        assert_eq!(limiter.remaining_at(much_later).per_minute, Some(5));
+    }
+
+    #[test]
+    fn daily_quota() {
+        let limiter = RateLimiter::new(RateLimit::new().per_minute(5).per_day(2));
+        let now = limiter.state.lock().unwrap().updated;
+        limiter.reserve_at(now).unwrap();
+        limiter.reserve_at(now).unwrap();
+        let result = limiter.reserve_at(now);
+        assert!(matches!(result, Err(Error::QuotaExhausted)));
+        // Rejected calls do not use the per-minute quota.
+        assert_eq!(limiter.remaining_at(now).per_minute, Some(3));
+
+        let later = now + Duration::from_secs(43_200);
+        assert_eq!(limiter.reserve_at(later).unwrap(), Duration::ZERO);
+        assert!(limiter.reserve_at(later).is_err());
+    }
+}
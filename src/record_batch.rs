@@ -0,0 +1,192 @@
+//! Conversion of API responses to [Apache Arrow](https://arrow.apache.org) record
+//! batches and Parquet files, available through the optional `arrow` feature.
+//!
+//! A [TimeSeries] holds one row per entry with the columns `symbol`, `date`
+//! (timezone-aware timestamp, in milliseconds), `open`, `high`, `low`, `close` and
+//! `volume`. [SearchResults] hold one row per match with the columns `symbol`, `name`,
+//! `type`, `region`, `market_open`, `market_close`, `timezone`, `currency` and
+//! `match_score`.
+use crate::tickers::SearchResults;
+use crate::time_series::{Entry, TimeSeries};
+use arrow_array::{
+    ArrayRef, Float64Array, RecordBatch, StringArray, Time32SecondArray, TimestampMillisecondArray,
+    UInt64Array,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use chrono::Timelike;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Conversion to an Arrow record batch.
+pub trait ToRecordBatch {
+    /// Convert into a record batch.
+    fn to_record_batch(&self) -> Result<RecordBatch, ArrowError>;
+
+    /// Write the record batch to a Parquet file at `path`, replacing any existing file.
+    fn write_parquet(&self, path: impl AsRef<Path>) -> Result<(), ParquetError> {
+        let batch = self.to_record_batch()?;
+        let file = File::create(path)?;
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+impl ToRecordBatch for TimeSeries {
+    fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        let time_zone = self.last_refreshed.timezone().name();
+        let date_type = DataType::Timestamp(TimeUnit::Millisecond, Some(time_zone.into()));
+        let schema = Schema::new(vec![
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("date", date_type, false),
+            Field::new("open", DataType::Float64, false),
+            Field::new("high", DataType::Float64, false),
+            Field::new("low", DataType::Float64, false),
+            Field::new("close", DataType::Float64, false),
+            Field::new("volume", DataType::UInt64, false),
+        ]);
+        let prices = |value: fn(&Entry) -> f64| -> ArrayRef {
+            let values: Float64Array = self
+                .entries
+                .iter()
+                .map(|entry| Some(value(entry)))
+                .collect();
+            Arc::new(values)
+        };
+        let symbols = vec![self.symbol.as_str(); self.entries.len()];
+        let dates: Vec<i64> = self
+            .entries
+            .iter()
+            .map(|entry| entry.date.timestamp_millis())
+            .collect();
+        let volumes: Vec<u64> = self.entries.iter().map(|entry| entry.volume).collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(symbols)),
+                Arc::new(TimestampMillisecondArray::from(dates).with_timezone(time_zone)),
+                prices(|entry| entry.open),
+                prices(|entry| entry.high),
+                prices(|entry| entry.low),
+                prices(|entry| entry.close),
+                Arc::new(UInt64Array::from(volumes)),
+            ],
+        )
+    }
+}
+
+impl ToRecordBatch for SearchResults {
+    fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        let schema = Schema::new(vec![
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("type", DataType::Utf8, false),
+            Field::new("region", DataType::Utf8, false),
+            Field::new("market_open", DataType::Time32(TimeUnit::Second), false),
+            Field::new("market_close", DataType::Time32(TimeUnit::Second), false),
+            Field::new("timezone", DataType::Utf8, false),
+            Field::new("currency", DataType::Utf8, false),
+            Field::new("match_score", DataType::Float64, false),
+        ]);
+        let strings = |value: fn(&crate::tickers::Entry) -> String| -> ArrayRef {
+            let values: StringArray = self
+                .entries
+                .iter()
+                .map(|entry| Some(value(entry)))
+                .collect();
+            Arc::new(values)
+        };
+        let times = |value: fn(&crate::tickers::Entry) -> chrono::NaiveTime| -> ArrayRef {
+            let values: Vec<i32> = self
+                .entries
+                .iter()
+                .map(|entry| value(entry).num_seconds_from_midnight() as i32)
+                .collect();
+            Arc::new(Time32SecondArray::from(values))
+        };
+        let scores: Vec<f64> = self.entries.iter().map(|entry| entry.match_score).collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                strings(|entry| entry.symbol.clone()),
+                strings(|entry| entry.name.clone()),
+                strings(|entry| entry.stock_type.clone()),
+                strings(|entry| entry.region.clone()),
+                times(|entry| entry.market_open),
+                times(|entry| entry.market_close),
+                strings(|entry| entry.timezone.to_string()),
+                strings(|entry| entry.currency.clone()),
+                Arc::new(Float64Array::from(scores)),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_series::{parser, Function};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn time_series() -> TimeSeries {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_daily.json");
+        parser::parse(&Function::Daily, data).unwrap()
+    }
+
+    #[test]
+    fn time_series_batch() {
+        let time_series = time_series();
+        let batch = time_series.to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), time_series.entries.len());
+        let dates = batch
+            .column_by_name("date")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(dates.timezone(), Some("US/Eastern"));
+        assert_eq!(
+            dates.value(0),
+            time_series.entries[0].date.timestamp_millis()
+        );
+    }
+
+    #[test]
+    fn search_results_batch() {
+        let data: &[u8] = include_bytes!("../tests/json/ticker_search_tesco.json");
+        let results = crate::tickers::parser::parse(None, data).unwrap();
+        let batch = results.to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), results.entries.len());
+        let symbols = batch
+            .column_by_name("symbol")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(symbols.value(0), results.entries[0].symbol);
+        assert_eq!(batch.column_by_name("market_open").unwrap().null_count(), 0);
+    }
+
+    #[test]
+    fn write_parquet() {
+        let path = std::env::temp_dir().join(format!(
+            "alphavantage-record-batch-{}.parquet",
+            std::process::id()
+        ));
+        let time_series = time_series();
+        time_series.write_parquet(&path).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batches, vec![time_series.to_record_batch().unwrap()]);
+    }
+}
@@ -0,0 +1,55 @@
+//! Typed requests for arbitrary API functions
+//!
+//! Implementing [ApiRequest] for a type allows it to be sent through
+//! [Client::execute](crate::Client::execute) (or the blocking equivalent),
+//! which makes it possible to call API functions not directly supported by
+//! the crate.
+use crate::deserialize::ResponseStatus;
+use crate::error::Error;
+use serde::Deserialize;
+use std::io::Read;
+
+/// A request for a given API function along with the means to parse its response.
+pub trait ApiRequest {
+    /// Type of the parsed response.
+    type Response;
+
+    /// Name of the API function (e.g. `TIME_SERIES_DAILY`).
+    fn function(&self) -> &str;
+
+    /// Query parameters sent along with the function, excluding the API key.
+    fn params(&self) -> Vec<(&str, &str)>;
+
+    /// Parse the body of a successful response.
+    fn parse<R: Read>(&self, reader: R) -> Result<Self::Response, Error>;
+}
+
+/// Request for an arbitrary function returning the unparsed JSON response.
+pub(crate) struct RawRequest<'a> {
+    function: &'a str,
+    params: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> RawRequest<'a> {
+    pub(crate) fn new(function: &'a str, params: &'a [(&'a str, &'a str)]) -> Self {
+        RawRequest { function, params }
+    }
+}
+
+impl ApiRequest for RawRequest<'_> {
+    type Response = serde_json::Value;
+
+    fn function(&self) -> &str {
+        self.function
+    }
+
+    fn params(&self) -> Vec<(&str, &str)> {
+        self.params.to_vec()
+    }
+
+    fn parse<R: Read>(&self, reader: R) -> Result<serde_json::Value, Error> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        ResponseStatus::deserialize(&value)?.check()?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,272 @@
+//! Resampling of time series to coarser intervals.
+//!
+//! Entries are grouped in the time zone of the time series and aggregated into a
+//! single entry per group, using the open of the first entry, the highest high, the
+//! lowest low, the close of the last entry and the total volume.
+use crate::time_series::{Entry, TimeSeries};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use std::time::Duration;
+
+/// Represents a calendar period to resample a time series to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalendarPeriod {
+    /// Weeks starting on Monday.
+    Weekly,
+    /// Calendar months.
+    Monthly,
+    /// Calendar quarters.
+    Quarterly,
+}
+
+impl CalendarPeriod {
+    /// Year and index of the period within the year for a given `date`.
+    fn key(self, date: NaiveDate) -> (i32, u32) {
+        match self {
+            CalendarPeriod::Weekly => {
+                let week = date.iso_week();
+                (week.year(), week.week())
+            }
+            CalendarPeriod::Monthly => (date.year(), date.month()),
+            CalendarPeriod::Quarterly => (date.year(), (date.month() - 1) / 3),
+        }
+    }
+}
+
+impl TimeSeries {
+    /// Aggregate the entries into bars spanning `duration`, such as 2 or 4 hours.
+    ///
+    /// Bars are aligned to midnight in the time zone of the time series and, like the
+    /// intraday entries returned by the API, are dated at the end of their interval:
+    /// an hourly bar dated 10:00 aggregates the entries dated after 9:00 up to 10:00.
+    /// Bars without entries are omitted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is shorter than one second.
+    pub fn resample(&self, duration: Duration) -> TimeSeries {
+        let seconds = duration.as_secs() as i64;
+        assert!(
+            seconds > 0,
+            "resampling duration must be at least one second"
+        );
+        let bucket = |date: &DateTime<Tz>| {
+            let local = date.naive_local().and_utc().timestamp();
+            (local - 1).div_euclid(seconds) * seconds + seconds
+        };
+        let entries = aggregate(&self.entries, bucket, |end, _, last| {
+            DateTime::from_timestamp(*end, 0)
+                .and_then(|end| {
+                    let time_zone = last.timezone();
+                    time_zone.from_local_datetime(&end.naive_utc()).latest()
+                })
+                .unwrap_or(*last)
+        });
+        self.with_entries(entries)
+    }
+
+    /// Aggregate the entries into one entry per calendar `period`, such as daily
+    /// entries into weekly entries.
+    ///
+    /// Entries are dated like the entry of the last trading day in each period, as in
+    /// the weekly and monthly time series returned by the API.
+    pub fn resample_calendar(&self, period: CalendarPeriod) -> TimeSeries {
+        let bucket = |date: &DateTime<Tz>| period.key(date.date_naive());
+        let entries = aggregate(&self.entries, bucket, |_, _, last| *last);
+        self.with_entries(entries)
+    }
+
+    fn with_entries(&self, entries: Vec<Entry>) -> TimeSeries {
+        TimeSeries {
+            symbol: self.symbol.clone(),
+            last_refreshed: self.last_refreshed,
+            entries,
+        }
+    }
+}
+
+/// Aggregate consecutive entries in the same bucket, dating each aggregated entry
+/// with `date(bucket, first date, last date)`.
+fn aggregate<K: PartialEq>(
+    entries: &[Entry],
+    bucket: impl Fn(&DateTime<Tz>) -> K,
+    date: impl Fn(&K, &DateTime<Tz>, &DateTime<Tz>) -> DateTime<Tz>,
+) -> Vec<Entry> {
+    let mut groups: Vec<(K, DateTime<Tz>, Entry)> = Vec::new();
+    for entry in entries {
+        let key = bucket(&entry.date);
+        match groups.last_mut() {
+            Some((current, _, last)) if *current == key => {
+                if entry.high > last.high {
+                    last.high = entry.high;
+                }
+                if entry.low < last.low {
+                    last.low = entry.low;
+                }
+                last.close = entry.close;
+                last.volume += entry.volume;
+                #[cfg(feature = "decimal")]
+                {
+                    last.decimals = last
+                        .decimals
+                        .zip(entry.decimals)
+                        .map(|(last, next)| last.aggregate(next));
+                }
+                last.date = entry.date;
+            }
+            _ => groups.push((key, entry.date, entry.clone())),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(key, first, mut entry)| {
+            entry.date = date(&key, &first, &entry.date);
+            entry
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use crate::time_series::{parser, Function, IntradayInterval};
+    use chrono_tz::US::Eastern;
+
+    fn time_series(function: Function, data: &[u8]) -> TimeSeries {
+        parser::parse(&function, data).unwrap()
+    }
+
+    fn expected(entries: &[&Entry], date: DateTime<Tz>) -> Entry {
+        Entry {
+            date,
+            open: entries[0].open,
+            high: entries
+                .iter()
+                .map(|entry| entry.high)
+                .fold(entries[0].high, |a, b| if b > a { b } else { a }),
+            low: entries
+                .iter()
+                .map(|entry| entry.low)
+                .fold(entries[0].low, |a, b| if b < a { b } else { a }),
+            close: entries[entries.len() - 1].close,
+            volume: entries.iter().map(|entry| entry.volume).sum(),
+            #[cfg(feature = "decimal")]
+            decimals: Some(crate::time_series::Decimals {
+                open: entries[0].open_decimal().unwrap(),
+                high: entries
+                    .iter()
+                    .filter_map(|e| e.high_decimal())
+                    .max()
+                    .unwrap(),
+                low: entries
+                    .iter()
+                    .filter_map(|e| e.low_decimal())
+                    .min()
+                    .unwrap(),
+                close: entries[entries.len() - 1].close_decimal().unwrap(),
+            }),
+        }
+    }
+
+    #[test]
+    fn resample_intraday() {
+        let time_series = time_series(
+            Function::IntraDay(IntradayInterval::OneMinute),
+            include_bytes!("../tests/json/time_series_intraday_1min.json"),
+        );
+        let resampled = time_series.resample(Duration::from_secs(15 * 60));
+        assert_eq!(resampled.entries.len(), 7);
+        assert_eq!(resampled.last_refreshed, time_series.last_refreshed);
+
+        let start = parse_date("2018-06-01 14:15:00", Eastern).unwrap();
+        let end = parse_date("2018-06-01 14:30:00", Eastern).unwrap();
+        let first: Vec<&Entry> = time_series
+            .entries
+            .iter()
+            .filter(|entry| entry.date > start && entry.date <= end)
+            .collect();
+        assert_eq!(resampled.entries[0], expected(&first, end));
+        let last: Vec<&Entry> = time_series.entries[time_series.entries.len() - 15..]
+            .iter()
+            .collect();
+        assert_eq!(
+            resampled.entries[6],
+            expected(&last, parse_date("2018-06-01 16:00:00", Eastern).unwrap())
+        );
+    }
+
+    #[test]
+    fn resample_session() {
+        let entry = time_series(
+            Function::IntraDay(IntradayInterval::OneMinute),
+            include_bytes!("../tests/json/time_series_intraday_1min.json"),
+        )
+        .entries[0]
+            .clone();
+        let open = parse_date("2018-06-01 09:30:00", Eastern).unwrap();
+        let entries: Vec<Entry> = (1..=390)
+            .map(|minute| Entry {
+                date: open + chrono::Duration::minutes(minute),
+                ..entry.clone()
+            })
+            .collect();
+        let session = TimeSeries {
+            symbol: "MSFT".to_string(),
+            last_refreshed: entries[389].date,
+            entries,
+        };
+        let resampled = session.resample(Duration::from_secs(60 * 60));
+        let dates: Vec<String> = resampled
+            .entries
+            .iter()
+            .map(|entry| entry.date.format("%H:%M").to_string())
+            .collect();
+        assert_eq!(
+            dates,
+            vec!["10:00", "11:00", "12:00", "13:00", "14:00", "15:00", "16:00"]
+        );
+        assert_eq!(resampled.entries[0].volume, 30 * entry.volume);
+        assert_eq!(resampled.entries[1].volume, 60 * entry.volume);
+    }
+
+    #[test]
+    fn resample_weekly() {
+        let time_series = time_series(
+            Function::Daily,
+            include_bytes!("../tests/json/time_series_daily.json"),
+        );
+        let resampled = time_series.resample_calendar(CalendarPeriod::Weekly);
+        let last_week: Vec<&Entry> = time_series
+            .entries
+            .iter()
+            .filter(|entry| entry.date >= parse_date("2018-06-04", Eastern).unwrap())
+            .collect();
+        let last = resampled.entries.last().unwrap();
+        assert_eq!(
+            *last,
+            expected(&last_week, parse_date("2018-06-08", Eastern).unwrap())
+        );
+        assert_eq!(
+            resampled.entries[resampled.entries.len() - 2].date,
+            parse_date("2018-06-01", Eastern).unwrap()
+        );
+    }
+
+    #[test]
+    fn resample_monthly_and_quarterly() {
+        let time_series = time_series(
+            Function::Daily,
+            include_bytes!("../tests/json/time_series_daily.json"),
+        );
+        let monthly = time_series.resample_calendar(CalendarPeriod::Monthly);
+        let quarterly = time_series.resample_calendar(CalendarPeriod::Quarterly);
+        assert!(monthly.entries.len() > quarterly.entries.len());
+        for period in &[monthly, quarterly] {
+            let volume: u64 = period.entries.iter().map(|entry| entry.volume).sum();
+            let total: u64 = time_series.entries.iter().map(|entry| entry.volume).sum();
+            assert_eq!(volume, total);
+            assert_eq!(period.entries[0].open, time_series.entries[0].open);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Automatic retries for transient failures
+//!
+//! A [RetryPolicy] can be set on a client so that calls failing due to
+//! connection errors, timeouts, server errors or rate limiting (including HTTP
+//! 429) are retried with exponential backoff. Errors returned by the API for
+//! invalid calls (e.g. an unknown symbol) are never retried.
+use crate::error::Error;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Policy determining how failed calls are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy making up to 3 attempts, with a backoff starting at 1 second
+    /// and doubling up to 60 seconds.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Set the maximum number of attempts for a call, including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry and the maximum delay between retries.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Set the factor the delay is multiplied by after each retry.
+    pub fn multiplier(mut self, multiplier: f64) -> RetryPolicy {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Set the fraction (between 0 and 1) by which each delay is randomly reduced.
+    pub fn jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Return the delay before retrying a call which failed with `error` on the given
+    /// `attempt` (starting at 1), or `None` if it should not be retried.
+    ///
+    /// Calls rejected with a `Retry-After` delay are retried no earlier than requested,
+    /// and are not retried if the delay is longer than the maximum backoff.
+    pub(crate) fn retry_delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !is_transient(error) {
+            return None;
+        }
+        let backoff = self.initial_backoff.as_secs_f64()
+            * self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let delay = Duration::from_secs_f64(backoff * (1.0 - self.jitter * random_fraction()));
+        match error {
+            Error::TooManyRequests(Some(retry_after)) if *retry_after > self.max_backoff => None,
+            Error::TooManyRequests(Some(retry_after)) => Some(delay.max(*retry_after)),
+            _ => Some(delay),
+        }
+    }
+}
+
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::ConnectionError(_) | Error::RateLimited(_) | Error::TooManyRequests(_) => true,
+        Error::ServerError(status) => *status == 429 || *status >= 500,
+        Error::QuotaExhausted
+        | Error::ParsingError(_)
+        | Error::APIError(_)
+        | Error::PremiumEndpoint(_)
+        | Error::ConfigurationError(_)
+        | Error::StorageError(_)
+        | Error::NoRecordedResponse(_)
+        | Error::InvalidSymbol(_)
+        | Error::InvalidCurrencyCode(_) => false,
+    }
+}
+
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_transient_errors() {
+        let policy = RetryPolicy::new().max_attempts(5).jitter(0.0);
+        let error = Error::ServerError(503);
+        assert_eq!(policy.retry_delay(1, &error), Some(Duration::from_secs(1)));
+        assert_eq!(policy.retry_delay(2, &error), Some(Duration::from_secs(2)));
+        assert_eq!(policy.retry_delay(4, &error), Some(Duration::from_secs(8)));
+        assert_eq!(policy.retry_delay(5, &error), None);
+        assert!(policy
+            .retry_delay(1, &Error::ConnectionError("timeout".into()))
+            .is_some());
+        assert!(policy
+            .retry_delay(1, &Error::RateLimited("note".into()))
+            .is_some());
+        assert_eq!(
+            policy.retry_delay(1, &Error::TooManyRequests(None)),
+            Some(Duration::from_secs(1))
+        );
+        assert!(policy.retry_delay(1, &Error::ServerError(429)).is_some());
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let policy = RetryPolicy::new().max_attempts(5).jitter(0.0);
+        let error = Error::TooManyRequests(Some(Duration::from_secs(30)));
+        assert_eq!(policy.retry_delay(1, &error), Some(Duration::from_secs(30)));
+        let error = Error::TooManyRequests(Some(Duration::from_millis(10)));
+        assert_eq!(policy.retry_delay(3, &error), Some(Duration::from_secs(4)));
+        let error = Error::TooManyRequests(Some(Duration::from_secs(3600)));
+        assert_eq!(policy.retry_delay(1, &error), None);
+    }
+
+    #[test]
+    fn does_not_retry_invalid_calls() {
+        let policy = RetryPolicy::new();
+        assert_eq!(policy.retry_delay(1, &Error::ServerError(404)), None);
+        assert_eq!(
+            policy.retry_delay(1, &Error::APIError("Invalid API call".into())),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(1, &Error::PremiumEndpoint("premium".into())),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(1, &Error::ParsingError("missing".into())),
+            None
+        );
+        assert_eq!(policy.retry_delay(1, &Error::QuotaExhausted), None);
+    }
+
+    #[test]
+    fn backoff_is_capped_and_jittered() {
+        let policy = RetryPolicy::new()
+            .max_attempts(10)
+            .backoff(Duration::from_secs(1), Duration::from_secs(5));
+        for attempt in 1..10 {
+            let delay = policy
+                .retry_delay(attempt, &Error::ServerError(500))
+                .unwrap();
+            assert!(delay <= Duration::from_secs(5));
+        }
+        let delay = policy.retry_delay(9, &Error::ServerError(500)).unwrap();
+        assert!(delay >= Duration::from_millis(2500));
+    }
+}
@@ -0,0 +1,93 @@
+//! Serialization of date and time types which are not supported by chrono's serde
+//! implementation, used by the optional `serde` feature.
+
+/// Serialize a `DateTime<Tz>` as an RFC 3339 timestamp followed by the IANA time zone
+/// (e.g. `2018-06-01T14:21:00-04:00[US/Eastern]`).
+pub(crate) mod date_time {
+    use chrono::{DateTime, SecondsFormat};
+    use chrono_tz::Tz;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::Serializer;
+
+    pub(crate) fn serialize<S: Serializer>(
+        date: &DateTime<Tz>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value = format!(
+            "{}[{}]",
+            date.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            date.timezone().name()
+        );
+        serializer.serialize_str(&value)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Tz>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let (timestamp, time_zone) = value
+            .strip_suffix(']')
+            .and_then(|value| value.split_once('['))
+            .ok_or_else(|| de::Error::custom(format!("missing time zone in {}", value)))?;
+        let time_zone: Tz = time_zone.parse().map_err(de::Error::custom)?;
+        let date = DateTime::parse_from_rfc3339(timestamp).map_err(de::Error::custom)?;
+        Ok(date.with_timezone(&time_zone))
+    }
+}
+
+/// Serialize a `FixedOffset` as a UTC offset (e.g. `+01:00`).
+pub(crate) mod offset {
+    use chrono::FixedOffset;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::Serializer;
+
+    pub(crate) fn serialize<S: Serializer>(
+        offset: &FixedOffset,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&offset.to_string())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FixedOffset, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserialize::parse_date;
+    use chrono::{DateTime, FixedOffset};
+    use chrono_tz::Tz;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Value {
+        #[serde(with = "super::date_time")]
+        date: DateTime<Tz>,
+        #[serde(with = "super::offset")]
+        offset: FixedOffset,
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = Value {
+            date: parse_date("2018-06-01 14:21:00", chrono_tz::US::Eastern).unwrap(),
+            offset: FixedOffset::east_opt(-(5 * 3600 + 30 * 60)).unwrap(),
+        };
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"date":"2018-06-01T14:21:00-04:00[US/Eastern]","offset":"-05:30"}"#
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn missing_time_zone() {
+        let json = r#"{"date":"2018-06-01T14:21:00-04:00","offset":"+00:00"}"#;
+        assert!(serde_json::from_str::<Value>(json).is_err());
+    }
+}
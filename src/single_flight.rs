@@ -0,0 +1,137 @@
+//! Sharing of concurrent identical calls made by the asynchronous client.
+//!
+//! Only the unparsed response is shared: each caller parses it with its own request,
+//! so that a parsing error is never returned to a caller expecting another type.
+use crate::cache::CacheKey;
+use crate::error::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Unparsed response body of a call shared by all concurrent callers.
+pub(crate) type SharedBody = Result<Arc<Vec<u8>>, Error>;
+
+/// Calls currently in flight, so that concurrent identical calls are only made once.
+#[derive(Debug, Default)]
+pub(crate) struct SingleFlight {
+    calls: Mutex<HashMap<CacheKey, Arc<OnceCell<SharedBody>>>>,
+}
+
+impl SingleFlight {
+    pub(crate) fn new() -> SingleFlight {
+        SingleFlight::default()
+    }
+
+    /// Return the call in flight for `key`, registering a new one if there is none.
+    pub(crate) fn join(&self, key: &CacheKey) -> Arc<OnceCell<SharedBody>> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.entry(key.clone()).or_default().clone()
+    }
+
+    /// Stop sharing `call` with new callers once it has completed.
+    pub(crate) fn leave(&self, key: &CacheKey, call: &Arc<OnceCell<SharedBody>>) {
+        let mut calls = self.calls.lock().unwrap();
+        if calls
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, call))
+        {
+            calls.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::transport::{HttpResponse, MockTransport, ResponseFuture, Transport};
+    use crate::Client;
+    use reqwest::Url;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct SlowTransport {
+        inner: MockTransport,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Transport for SlowTransport {
+        fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                self.inner.fetch(url).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_calls_are_shared() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let transport = SlowTransport {
+            inner: MockTransport::new().with_response(
+                "CURRENCY_EXCHANGE_RATE",
+                &[("from_currency", "EUR"), ("to_currency", "USD")],
+                HttpResponse::ok(&include_bytes!("../tests/json/currency_exchange_rate.json")[..]),
+            ),
+            calls: calls.clone(),
+        };
+        let client = Client::builder("secret")
+            .transport(transport)
+            .single_flight(true)
+            .build()
+            .unwrap();
+        let (first, second, raw) = tokio::join!(
+            client.get_exchange_rate("EUR", "USD"),
+            client.get_exchange_rate("EUR", "USD"),
+            client.call_raw(
+                "CURRENCY_EXCHANGE_RATE",
+                &[("to_currency", "USD"), ("from_currency", "EUR")]
+            ),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap(), second.unwrap());
+        assert!(raw
+            .unwrap()
+            .get("Realtime Currency Exchange Rate")
+            .is_some());
+
+        let (missing, other) = tokio::join!(
+            client.get_exchange_rate("USD", "EUR"),
+            client.get_exchange_rate("USD", "EUR"),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(missing.is_err() && other.is_err());
+
+        client.get_exchange_rate("EUR", "USD").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn each_caller_parses_the_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let transport = SlowTransport {
+            inner: MockTransport::new().with_response(
+                "CURRENCY_EXCHANGE_RATE",
+                &[("from_currency", "EUR"), ("to_currency", "USD")],
+                HttpResponse::ok(r#"{"Unexpected": {}}"#),
+            ),
+            calls: calls.clone(),
+        };
+        let client = Client::builder("secret")
+            .transport(transport)
+            .single_flight(true)
+            .build()
+            .unwrap();
+        let (typed, raw) = tokio::join!(
+            client.get_exchange_rate("EUR", "USD"),
+            client.call_raw(
+                "CURRENCY_EXCHANGE_RATE",
+                &[("from_currency", "EUR"), ("to_currency", "USD")]
+            ),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(typed, Err(Error::ParsingError(_))));
+        assert!(raw.unwrap().get("Unexpected").is_some());
+    }
+}
@@ -0,0 +1,276 @@
+//! Return, volatility and drawdown statistics of time series.
+//!
+//! Statistics are computed from the close prices of the entries as `f64`. Annualized
+//! statistics take the number of entries per year, such as 252 for daily, 52 for
+//! weekly or 12 for monthly time series.
+use crate::analytics::Point;
+use crate::time_series::{Entry, TimeSeries};
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+/// Represents the largest decline from a peak close to a subsequent trough close.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    /// Date of the peak.
+    pub start: DateTime<Tz>,
+    /// Date of the trough.
+    pub end: DateTime<Tz>,
+    /// Decline from the peak as a fraction of the peak (e.g. 0.25 for a 25% decline).
+    pub depth: f64,
+}
+
+impl TimeSeries {
+    /// Simple returns between consecutive close prices, dated at the later entry.
+    pub fn returns(&self) -> Vec<Point<f64>> {
+        returns(&self.entries, |previous, close| close / previous - 1.0)
+    }
+
+    /// Logarithmic returns between consecutive close prices, dated at the later entry.
+    pub fn log_returns(&self) -> Vec<Point<f64>> {
+        returns(&self.entries, |previous, close| (close / previous).ln())
+    }
+
+    /// Annualized volatility of the logarithmic returns, or `None` if there are fewer
+    /// than three entries.
+    pub fn volatility(&self, periods_per_year: f64) -> Option<f64> {
+        volatility(&values(&self.log_returns()), periods_per_year)
+    }
+
+    /// Annualized Sharpe ratio of the simple returns given an annual `risk_free_rate`
+    /// (e.g. 0.02 for 2%), or `None` if there are fewer than three entries or the
+    /// returns do not vary.
+    pub fn sharpe_ratio(&self, risk_free_rate: f64, periods_per_year: f64) -> Option<f64> {
+        sharpe_ratio(&values(&self.returns()), risk_free_rate, periods_per_year)
+    }
+
+    /// Maximum drawdown, or `None` if the close price never declines.
+    pub fn max_drawdown(&self) -> Option<Drawdown> {
+        max_drawdown(&self.entries)
+    }
+
+    /// Annualized volatility over each `window` consecutive returns, dated at the last
+    /// entry of the window.
+    pub fn rolling_volatility(&self, window: usize, periods_per_year: f64) -> Vec<Point<f64>> {
+        rolling(&self.log_returns(), window, |returns| {
+            volatility(returns, periods_per_year)
+        })
+    }
+
+    /// Annualized Sharpe ratio over each `window` consecutive returns, dated at the last
+    /// entry of the window.
+    pub fn rolling_sharpe_ratio(
+        &self,
+        window: usize,
+        risk_free_rate: f64,
+        periods_per_year: f64,
+    ) -> Vec<Point<f64>> {
+        rolling(&self.returns(), window, |returns| {
+            sharpe_ratio(returns, risk_free_rate, periods_per_year)
+        })
+    }
+
+    /// Maximum drawdown over each `window` consecutive entries, dated at the last entry
+    /// of the window. Windows in which the close price never declines are skipped.
+    pub fn rolling_max_drawdown(&self, window: usize) -> Vec<Point<Drawdown>> {
+        if window == 0 {
+            return Vec::new();
+        }
+        self.entries
+            .windows(window)
+            .filter_map(|entries| {
+                max_drawdown(entries).map(|drawdown| Point {
+                    date: entries[window - 1].date,
+                    value: drawdown,
+                })
+            })
+            .collect()
+    }
+}
+
+fn returns(entries: &[Entry], value: impl Fn(f64, f64) -> f64) -> Vec<Point<f64>> {
+    entries
+        .windows(2)
+        .map(|pair| Point {
+            date: pair[1].date,
+            value: value(pair[0].close, pair[1].close),
+        })
+        .collect()
+}
+
+fn values(points: &[Point<f64>]) -> Vec<f64> {
+    points.iter().map(|point| point.value).collect()
+}
+
+/// Mean and sample standard deviation of `values`.
+fn mean_deviation(values: &[f64]) -> Option<(f64, f64)> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (values.len() - 1) as f64;
+    Some((mean, variance.sqrt()))
+}
+
+fn volatility(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    mean_deviation(returns).map(|(_, deviation)| deviation * periods_per_year.sqrt())
+}
+
+fn sharpe_ratio(returns: &[f64], risk_free_rate: f64, periods_per_year: f64) -> Option<f64> {
+    let (mean, deviation) = mean_deviation(returns)?;
+    if deviation == 0.0 {
+        return None;
+    }
+    let excess = mean - risk_free_rate / periods_per_year;
+    Some(excess / deviation * periods_per_year.sqrt())
+}
+
+fn max_drawdown(entries: &[Entry]) -> Option<Drawdown> {
+    let mut peak: Option<(f64, DateTime<Tz>)> = None;
+    let mut max: Option<Drawdown> = None;
+    for entry in entries {
+        let close = entry.close;
+        match peak {
+            Some((high, _)) if close <= high => {
+                let depth = 1.0 - close / high;
+                if depth > 0.0 && max.is_none_or(|max| depth > max.depth) {
+                    max = peak.map(|(_, start)| Drawdown {
+                        start,
+                        end: entry.date,
+                        depth,
+                    });
+                }
+            }
+            _ => peak = Some((close, entry.date)),
+        }
+    }
+    max
+}
+
+fn rolling(
+    points: &[Point<f64>],
+    window: usize,
+    statistic: impl Fn(&[f64]) -> Option<f64>,
+) -> Vec<Point<f64>> {
+    if window == 0 || window > points.len() {
+        return Vec::new();
+    }
+    let values = values(points);
+    values
+        .windows(window)
+        .zip(&points[window - 1..])
+        .filter_map(|(values, point)| {
+            statistic(values).map(|value| Point {
+                date: point.date,
+                value,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::parse_date;
+    use chrono::Duration;
+    use chrono_tz::US::Eastern;
+
+    fn series(closes: &[f64]) -> TimeSeries {
+        let start = parse_date("2018-06-01", Eastern).unwrap();
+        let entries: Vec<Entry> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Entry {
+                date: start + Duration::days(i as i64),
+                open: *close,
+                high: *close,
+                low: *close,
+                close: *close,
+                volume: 1000,
+                #[cfg(feature = "decimal")]
+                decimals: None,
+            })
+            .collect();
+        TimeSeries {
+            symbol: "MSFT".to_string(),
+            last_refreshed: entries.last().unwrap().date,
+            entries,
+        }
+    }
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn returns() {
+        let time_series = series(&[100.0, 110.0, 99.0]);
+        let returns = time_series.returns();
+        assert_eq!(returns.len(), 2);
+        assert_eq!(returns[0].date, time_series.entries[1].date);
+        assert_close(returns[0].value, 0.1);
+        assert_close(returns[1].value, -0.1);
+        let log_returns = time_series.log_returns();
+        assert_close(log_returns[0].value, 1.1f64.ln());
+        assert_close(log_returns[1].value, 0.9f64.ln());
+    }
+
+    #[test]
+    fn volatility_and_sharpe_ratio() {
+        let time_series = series(&[100.0, 110.0, 99.0, 108.9]);
+        // Simple returns are 0.1, -0.1 and 0.1.
+        let deviation = (0.04f64 / 3.0).sqrt();
+        assert_close(
+            time_series.sharpe_ratio(0.0, 252.0).unwrap(),
+            (0.1 / 3.0) / deviation * 252f64.sqrt(),
+        );
+        assert_close(
+            time_series.sharpe_ratio(0.252, 252.0).unwrap(),
+            (0.1 / 3.0 - 0.001) / deviation * 252f64.sqrt(),
+        );
+        let log_returns: Vec<f64> = [1.1f64, 0.9, 1.1].iter().map(|r| r.ln()).collect();
+        let mean = log_returns.iter().sum::<f64>() / 3.0;
+        let variance = log_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 2.0;
+        assert_close(
+            time_series.volatility(252.0).unwrap(),
+            variance.sqrt() * 252f64.sqrt(),
+        );
+        assert!(series(&[100.0, 110.0]).volatility(252.0).is_none());
+        assert!(series(&[100.0, 100.0, 100.0])
+            .sharpe_ratio(0.0, 252.0)
+            .is_none());
+    }
+
+    #[test]
+    fn max_drawdown() {
+        let time_series = series(&[100.0, 120.0, 90.0, 110.0, 60.0, 130.0, 117.0]);
+        let drawdown = time_series.max_drawdown().unwrap();
+        assert_eq!(drawdown.start, time_series.entries[1].date);
+        assert_eq!(drawdown.end, time_series.entries[4].date);
+        assert_close(drawdown.depth, 0.5);
+        assert!(series(&[100.0, 110.0, 120.0]).max_drawdown().is_none());
+    }
+
+    #[test]
+    fn rolling() {
+        let time_series = series(&[100.0, 120.0, 90.0, 110.0, 60.0, 130.0, 117.0]);
+        let drawdowns = time_series.rolling_max_drawdown(3);
+        assert_eq!(drawdowns.len(), 5);
+        assert_eq!(drawdowns[0].date, time_series.entries[2].date);
+        assert_close(drawdowns[0].value.depth, 0.25);
+        assert_close(drawdowns[4].value.depth, 0.1);
+
+        let volatility = time_series.rolling_volatility(3, 252.0);
+        assert_eq!(volatility.len(), 4);
+        assert_eq!(volatility[0].date, time_series.entries[3].date);
+        let sharpe_ratio = time_series.rolling_sharpe_ratio(3, 0.0, 252.0);
+        assert_eq!(sharpe_ratio.len(), 4);
+        assert!(time_series.rolling_volatility(0, 252.0).is_empty());
+        assert!(time_series.rolling_volatility(200, 252.0).is_empty());
+        assert!(time_series.rolling_sharpe_ratio(7, 0.0, 252.0).is_empty());
+        assert!(time_series.rolling_max_drawdown(200).is_empty());
+    }
+}
@@ -0,0 +1,442 @@
+//! Local persistence of time series in SQLite, available through the optional `store`
+//! feature.
+//!
+//! Time series are keyed by symbol, [Function] and intraday interval. Prices are stored
+//! as text so that they are read back exactly, including their exact decimal values
+//! with the `decimal` feature.
+use crate::deserialize::Price;
+use crate::error::Error;
+use crate::symbol::Symbol;
+use crate::time_series::{Entry, Function, OutputSize, TimeSeries};
+use crate::Client;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Number of data points returned by the API for compact time series.
+const COMPACT_SIZE: i64 = 100;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS series (
+        symbol TEXT NOT NULL,
+        function TEXT NOT NULL,
+        interval TEXT NOT NULL,
+        time_zone TEXT NOT NULL,
+        last_refreshed INTEGER NOT NULL,
+        PRIMARY KEY (symbol, function, interval)
+    );
+    CREATE TABLE IF NOT EXISTS entries (
+        symbol TEXT NOT NULL,
+        function TEXT NOT NULL,
+        interval TEXT NOT NULL,
+        date INTEGER NOT NULL,
+        open TEXT NOT NULL,
+        high TEXT NOT NULL,
+        low TEXT NOT NULL,
+        close TEXT NOT NULL,
+        volume INTEGER NOT NULL,
+        PRIMARY KEY (symbol, function, interval, date)
+    );
+";
+
+/// A SQLite store of time series.
+#[derive(Debug)]
+pub struct Store {
+    connection: Mutex<Connection>,
+}
+
+/// Columns identifying a time series in the store.
+struct Key<'a> {
+    symbol: &'a str,
+    function: &'static str,
+    interval: &'static str,
+}
+
+impl<'a> Key<'a> {
+    fn new(symbol: &'a Symbol, function: &Function) -> Key<'a> {
+        let interval = match function {
+            Function::IntraDay(interval) => interval.to_string(),
+            _ => "",
+        };
+        Key {
+            symbol: symbol.as_str(),
+            function: function.into(),
+            interval,
+        }
+    }
+}
+
+impl Store {
+    /// Open the store in the SQLite database at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Store, Error> {
+        Store::new(Connection::open(path)?)
+    }
+
+    /// Open a store kept in memory.
+    pub fn open_in_memory() -> Result<Store, Error> {
+        Store::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Store, Error> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Insert or update the entries of `time_series` retrieved with `function`, and
+    /// record its last refreshed date unless a later one is already recorded. Returns
+    /// the number of entries written.
+    pub fn insert(&self, function: &Function, time_series: &TimeSeries) -> Result<usize, Error> {
+        self.upsert(
+            &Symbol::from(&time_series.symbol),
+            function,
+            time_series,
+            None,
+        )
+    }
+
+    /// Load the time series for `symbol` retrieved with `function`, if any.
+    pub fn load(&self, symbol: &str, function: &Function) -> Result<Option<TimeSeries>, Error> {
+        let symbol = Symbol::from(symbol);
+        let key = Key::new(&symbol, function);
+        let connection = self.connection.lock().unwrap();
+        let series = connection
+            .query_row(
+                "SELECT time_zone, last_refreshed FROM series
+                 WHERE symbol = ?1 AND function = ?2 AND interval = ?3",
+                params![key.symbol, key.function, key.interval],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+        let (time_zone, last_refreshed) = match series {
+            Some(series) => series,
+            None => return Ok(None),
+        };
+        let time_zone: Tz = time_zone
+            .parse()
+            .map_err(|e| Error::StorageError(format!("invalid time zone {}: {}", time_zone, e)))?;
+
+        let mut statement = connection.prepare(
+            "SELECT date, open, high, low, close, volume FROM entries
+             WHERE symbol = ?1 AND function = ?2 AND interval = ?3 ORDER BY date",
+        )?;
+        let rows = statement.query_map(params![key.symbol, key.function, key.interval], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                [
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ],
+                row.get::<_, i64>(5)?,
+            ))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (date, [open, high, low, close], volume) = row?;
+            let price = |value: &str| {
+                value
+                    .parse::<Price>()
+                    .map_err(|_| Error::StorageError(format!("invalid price {}", value)))
+            };
+            let (open, high, low, close) =
+                (price(&open)?, price(&high)?, price(&low)?, price(&close)?);
+            entries.push(Entry {
+                date: timestamp(date, time_zone)?,
+                open: open.value,
+                high: high.value,
+                low: low.value,
+                close: close.value,
+                volume: volume as u64,
+                #[cfg(feature = "decimal")]
+                decimals: crate::time_series::Decimals::from_prices(open, high, low, close),
+            });
+        }
+        Ok(Some(TimeSeries {
+            symbol: symbol.to_string(),
+            last_refreshed: timestamp(last_refreshed, time_zone)?,
+            entries,
+        }))
+    }
+
+    /// Retrieve the time series for `symbol` using `function` and store the entries
+    /// which are not yet stored. Returns the number of entries written.
+    ///
+    /// Compact data is requested when the latest stored entry is recent enough to be
+    /// covered by it, and full data otherwise. Full data is also requested when the
+    /// compact data turns out not to cover the latest stored entry.
+    pub async fn sync(
+        &self,
+        client: &Client,
+        symbol: &str,
+        function: &Function,
+    ) -> Result<usize, Error> {
+        self.sync_at(client, symbol, function, Utc::now()).await
+    }
+
+    async fn sync_at(
+        &self,
+        client: &Client,
+        symbol: &str,
+        function: &Function,
+        now: DateTime<Utc>,
+    ) -> Result<usize, Error> {
+        let symbol = Symbol::from(symbol);
+        let latest = self.latest_date(&symbol, function)?;
+        let recent = latest.is_some_and(|latest| now < compact_end(latest, function));
+        let output_size = if recent {
+            OutputSize::Compact
+        } else {
+            OutputSize::Full
+        };
+        let mut time_series = client
+            .get_time_series(function, &symbol, output_size)
+            .await?;
+        let covered = time_series
+            .entries
+            .first()
+            .is_some_and(|first| latest.is_some_and(|latest| first.date.timestamp() <= latest));
+        if recent && !covered {
+            time_series = client
+                .get_time_series(function, &symbol, OutputSize::Full)
+                .await?;
+        }
+        self.upsert(&symbol, function, &time_series, latest)
+    }
+
+    /// Unix timestamp of the latest entry stored for `symbol` and `function`.
+    fn latest_date(&self, symbol: &Symbol, function: &Function) -> Result<Option<i64>, Error> {
+        let key = Key::new(symbol, function);
+        let connection = self.connection.lock().unwrap();
+        let latest = connection.query_row(
+            "SELECT MAX(date) FROM entries WHERE symbol = ?1 AND function = ?2 AND interval = ?3",
+            params![key.symbol, key.function, key.interval],
+            |row| row.get(0),
+        )?;
+        Ok(latest)
+    }
+
+    /// Write the entries dated at or after `since` under `symbol`, replacing the latest
+    /// stored entry which may have been incomplete when it was retrieved.
+    fn upsert(
+        &self,
+        symbol: &Symbol,
+        function: &Function,
+        time_series: &TimeSeries,
+        since: Option<i64>,
+    ) -> Result<usize, Error> {
+        let key = Key::new(symbol, function);
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO series (symbol, function, interval, time_zone, last_refreshed)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (symbol, function, interval)
+             DO UPDATE SET time_zone = excluded.time_zone, last_refreshed = excluded.last_refreshed
+             WHERE excluded.last_refreshed > series.last_refreshed",
+            params![
+                key.symbol,
+                key.function,
+                key.interval,
+                time_series.last_refreshed.timezone().name(),
+                time_series.last_refreshed.timestamp(),
+            ],
+        )?;
+        let mut written = 0;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO entries (symbol, function, interval, date, open, high, low, close, volume)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT (symbol, function, interval, date)
+                 DO UPDATE SET open = excluded.open, high = excluded.high, low = excluded.low,
+                     close = excluded.close, volume = excluded.volume",
+            )?;
+            for entry in &time_series.entries {
+                let date = entry.date.timestamp();
+                if since.is_some_and(|since| date < since) {
+                    continue;
+                }
+                let [open, high, low, close] = prices(entry);
+                written += statement.execute(params![
+                    key.symbol,
+                    key.function,
+                    key.interval,
+                    date,
+                    open,
+                    high,
+                    low,
+                    close,
+                    entry.volume as i64,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(written)
+    }
+}
+
+/// Date up to which compact data retrieved after the entry dated `latest` still
+/// includes that entry.
+/// Open, high, low and close prices of `entry` as text, using their exact decimal
+/// values when available.
+fn prices(entry: &Entry) -> [String; 4] {
+    #[cfg(feature = "decimal")]
+    if let Some(decimals) = entry.decimals {
+        return [
+            decimals.open.to_string(),
+            decimals.high.to_string(),
+            decimals.low.to_string(),
+            decimals.close.to_string(),
+        ];
+    }
+    [
+        entry.open.to_string(),
+        entry.high.to_string(),
+        entry.low.to_string(),
+        entry.close.to_string(),
+    ]
+}
+
+fn compact_end(latest: i64, function: &Function) -> DateTime<Utc> {
+    let latest = Utc.timestamp_opt(latest, 0).unwrap();
+    match function {
+        Function::IntraDay(interval) => {
+            latest + chrono::Duration::minutes(interval.minutes() * COMPACT_SIZE)
+        }
+        Function::Daily => latest + chrono::Duration::days(COMPACT_SIZE),
+        Function::Weekly => latest + chrono::Duration::weeks(COMPACT_SIZE),
+        Function::Monthly => latest + chrono::Duration::days(30 * COMPACT_SIZE),
+    }
+}
+
+fn timestamp(value: i64, time_zone: Tz) -> Result<DateTime<Tz>, Error> {
+    time_zone
+        .timestamp_opt(value, 0)
+        .single()
+        .ok_or_else(|| Error::StorageError(format!("invalid timestamp {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpResponse, MockTransport};
+
+    fn client(output_size: &str) -> Client {
+        let transport = MockTransport::new().with_response(
+            "TIME_SERIES_DAILY",
+            &[("symbol", "MSFT"), ("outputsize", output_size)],
+            HttpResponse::ok(&include_bytes!("../tests/json/time_series_daily.json")[..]),
+        );
+        Client::builder("secret")
+            .transport(transport)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sync() {
+        let store = Store::open_in_memory().unwrap();
+        assert!(store.load("MSFT", &Function::Daily).unwrap().is_none());
+
+        let written = store
+            .sync(&client("full"), "MSFT", &Function::Daily)
+            .await
+            .unwrap();
+        assert_eq!(written, 100);
+        let stored = store.load("MSFT", &Function::Daily).unwrap().unwrap();
+        let expected = client("full")
+            .get_time_series_daily_full("MSFT")
+            .await
+            .unwrap();
+        assert_eq!(stored, expected);
+
+        let latest = expected.entries.last().unwrap().date.with_timezone(&Utc);
+        let written = store
+            .sync_at(
+                &client("compact"),
+                "MSFT",
+                &Function::Daily,
+                latest + chrono::Duration::days(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 1);
+        assert!(store.load("MSFT", &Function::Weekly).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn last_refreshed_never_moves_backwards() {
+        let store = Store::open_in_memory().unwrap();
+        let time_series = client("full")
+            .get_time_series_daily_full("MSFT")
+            .await
+            .unwrap();
+        store.insert(&Function::Daily, &time_series).unwrap();
+
+        let mut older = time_series.clone();
+        older.entries.truncate(10);
+        older.last_refreshed = older.entries[9].date;
+        store.insert(&Function::Daily, &older).unwrap();
+        let stored = store.load("MSFT", &Function::Daily).unwrap().unwrap();
+        assert_eq!(stored.last_refreshed, time_series.last_refreshed);
+
+        let mut newer = time_series.clone();
+        newer.last_refreshed += chrono::Duration::hours(1);
+        store.insert(&Function::Daily, &newer).unwrap();
+        let stored = store.load("MSFT", &Function::Daily).unwrap().unwrap();
+        assert_eq!(stored.last_refreshed, newer.last_refreshed);
+    }
+
+    #[tokio::test]
+    async fn stale_store_requests_full_data() {
+        let store = Store::open_in_memory().unwrap();
+        let mut time_series = client("full")
+            .get_time_series_daily_full("MSFT")
+            .await
+            .unwrap();
+        time_series.entries.truncate(1);
+        store.insert(&Function::Daily, &time_series).unwrap();
+
+        let result = store
+            .sync(&client("compact"), "MSFT", &Function::Daily)
+            .await;
+        assert!(matches!(result, Err(Error::ServerError(404))));
+        let written = store
+            .sync(&client("full"), "MSFT", &Function::Daily)
+            .await
+            .unwrap();
+        assert_eq!(written, 100);
+    }
+
+    #[tokio::test]
+    async fn symbols_are_case_insensitive() {
+        let store = Store::open_in_memory().unwrap();
+        let written = store
+            .sync(&client("full"), "msft", &Function::Daily)
+            .await
+            .unwrap();
+        assert_eq!(written, 100);
+        let stored = store.load("msft", &Function::Daily).unwrap().unwrap();
+        assert_eq!(stored.symbol, "MSFT");
+        assert_eq!(
+            stored,
+            store.load("MSFT", &Function::Daily).unwrap().unwrap()
+        );
+
+        let latest = stored.entries.last().unwrap().date.with_timezone(&Utc);
+        let written = store
+            .sync_at(
+                &client("compact"),
+                "msft",
+                &Function::Daily,
+                latest + chrono::Duration::days(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 1);
+    }
+}
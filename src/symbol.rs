@@ -0,0 +1,150 @@
+//! Ticker symbols with validation.
+use crate::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Suffixes of the exchanges listing symbols outside of the United States, sorted.
+const EXCHANGE_SUFFIXES: &[&str] = &[
+    "AMS", "BRU", "BSE", "CPH", "DEX", "FRK", "HEL", "LIS", "LON", "MIL", "NSE", "OSL", "PAR",
+    "SAO", "SHH", "SHZ", "STO", "TRT", "TRV", "VIE",
+];
+
+/// Maximum length of a symbol without its exchange suffix.
+const MAX_BASE_LENGTH: usize = 12;
+
+/// Represents a ticker symbol, such as `MSFT`, `BRK.B` or `TSCO.LON`.
+///
+/// Symbols are converted to uppercase. Parsing a symbol validates it, while converting
+/// one from a string does not: use [Symbol::validate] to check it. The clients validate
+/// symbols before calling the API.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(String);
+
+impl Symbol {
+    /// Return the symbol as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Return the suffix of the exchange listing the symbol, if any (e.g. `LON` for
+    /// `TSCO.LON`).
+    pub fn exchange_suffix(&self) -> Option<&str> {
+        let (_, suffix) = self.0.rsplit_once('.')?;
+        EXCHANGE_SUFFIXES
+            .binary_search(&suffix)
+            .ok()
+            .map(|_| suffix)
+    }
+
+    /// Return whether the symbol is made of up to 12 letters, digits or dashes,
+    /// optionally followed by a share class of one or two characters or a known
+    /// exchange suffix.
+    pub fn is_valid(&self) -> bool {
+        let (base, suffix) = match self.0.rsplit_once('.') {
+            Some((base, suffix)) => (base, Some(suffix)),
+            None => (self.0.as_str(), None),
+        };
+        let valid_base = !base.is_empty()
+            && base.len() <= MAX_BASE_LENGTH
+            && base.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        let valid_suffix = suffix.is_none_or(|suffix| {
+            let share_class = (1..=2).contains(&suffix.len())
+                && suffix.chars().all(|c| c.is_ascii_alphanumeric());
+            share_class || self.exchange_suffix().is_some()
+        });
+        valid_base && valid_suffix
+    }
+
+    /// Return an [Error::InvalidSymbol] if the symbol is not valid.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::InvalidSymbol(self.0.clone()))
+        }
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let symbol = Symbol::from(s);
+        symbol.validate()?;
+        Ok(symbol)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(symbol: &str) -> Self {
+        Symbol(symbol.to_ascii_uppercase())
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(symbol: &String) -> Self {
+        Symbol::from(symbol.as_str())
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(mut symbol: String) -> Self {
+        symbol.make_ascii_uppercase();
+        Symbol(symbol)
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self {
+        symbol.clone()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for symbol in &[
+            "MSFT",
+            "BRK.B",
+            "BRK-B",
+            "TSCO.LON",
+            "63TA.FRK",
+            "RELIANCE.BSE",
+        ] {
+            let parsed: Symbol = symbol.parse().unwrap();
+            assert_eq!(parsed.to_string(), *symbol);
+        }
+        assert_eq!("tsco.lon".parse::<Symbol>().unwrap().as_str(), "TSCO.LON");
+        for symbol in &["", "MS FT", "MSFT.", ".LON", "TSCO.XYZ", "VERYLONGSYMBOL1"] {
+            assert!(
+                matches!(symbol.parse::<Symbol>(), Err(Error::InvalidSymbol(_))),
+                "{}",
+                symbol
+            );
+        }
+        assert!(!Symbol::from("MS FT").is_valid());
+    }
+
+    #[test]
+    fn exchange_suffix() {
+        assert!(EXCHANGE_SUFFIXES.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(Symbol::from("TSCO.LON").exchange_suffix(), Some("LON"));
+        assert_eq!(Symbol::from("BRK.B").exchange_suffix(), None);
+        assert_eq!(Symbol::from("MSFT").exchange_suffix(), None);
+    }
+}
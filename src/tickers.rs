@@ -1,7 +1,12 @@
+use crate::calendar::Exchange;
+use crate::error::Error;
+use crate::request::ApiRequest;
 use chrono::{FixedOffset, NaiveTime};
+use std::io::Read;
 
 /// Respresent a set of search results.
-#[derive(Debug, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SearchResults {
     /// The query that was searched.
     pub query: Option<String>,
@@ -11,6 +16,7 @@ pub struct SearchResults {
 
 /// Represents a set of values for a ticker
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     /// Symbol.
     pub symbol: String,
@@ -25,6 +31,7 @@ pub struct Entry {
     /// Market close time.
     pub market_close: NaiveTime,
     /// Timezone.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::offset"))]
     pub timezone: FixedOffset,
     /// Currency.
     pub currency: String,
@@ -32,14 +39,46 @@ pub struct Entry {
     pub match_score: f64,
 }
 
+impl Entry {
+    /// Return the exchange the ticker is traded on, if its region is known.
+    pub fn exchange(&self) -> Option<Exchange> {
+        Exchange::from_region(&self.region)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SearchRequest {
+    query: String,
+}
+
+impl SearchRequest {
+    pub(crate) fn new(query: &str) -> Self {
+        SearchRequest {
+            query: query.to_string(),
+        }
+    }
+}
+
+impl ApiRequest for SearchRequest {
+    type Response = SearchResults;
+
+    fn function(&self) -> &str {
+        "SYMBOL_SEARCH"
+    }
+
+    fn params(&self) -> Vec<(&str, &str)> {
+        vec![("keywords", self.query.as_str())]
+    }
+
+    fn parse<R: Read>(&self, reader: R) -> Result<SearchResults, Error> {
+        parser::parse(Some(self.query.clone()), reader)
+    }
+}
+
 pub(crate) mod parser {
     use super::*;
-    use crate::deserialize::{from_str, parse_time};
-    use crate::error::Error;
-    use chrono::FixedOffset;
-
+    use crate::deserialize::{from_str, parse_time, ResponseStatus};
     use serde::Deserialize;
-    use std::io::Read;
 
     fn parse_offset(offset: &str) -> Option<f64> {
         if let Some(sign) = offset.get(3..4) {
@@ -88,15 +127,18 @@ pub(crate) mod parser {
 
     #[derive(Debug, Deserialize)]
     struct SearchResultsHelper {
+        #[serde(flatten)]
+        status: ResponseStatus,
         #[serde(rename = "bestMatches")]
-        entries: Vec<EntryHelper>,
+        entries: Option<Vec<EntryHelper>>,
     }
 
     pub fn parse(query: Option<String>, reader: impl Read) -> Result<SearchResults, Error> {
         let helper: SearchResultsHelper = serde_json::from_reader(reader)?;
+        helper.status.check()?;
         let entries: Vec<Result<Entry, Error>> = helper
             .entries
-            .clone()
+            .ok_or_else(|| Error::ParsingError("missing search results".into()))?
             .into_iter()
             .map(|entry| -> Result<Entry, Error> {
                 let timezone = get_utc_offset_from_str(&entry.timezone)?;
@@ -152,6 +194,7 @@ mod tests {
                 match_score: 0.7273
             }
         );
+        assert_eq!(results.entries[0].exchange(), Some(Exchange::Lse));
     }
 
     #[test]
@@ -175,5 +218,26 @@ mod tests {
                 match_score: 0.5185
             }
         );
+        assert_eq!(results.entries[0].exchange(), Some(Exchange::Frankfurt));
+    }
+
+    #[test]
+    fn parse_rate_limited() {
+        let data: &[u8] = include_bytes!("../tests/json/rate_limit_note.json");
+        let result = parser::parse(None, BufReader::new(data));
+        assert!(matches!(result, Err(crate::Error::RateLimited(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let data: &[u8] = include_bytes!("../tests/json/ticker_search_tencent.json");
+        let results = parser::parse(Some("tencent".into()), BufReader::new(data))
+            .expect("failed to parse tencent search results");
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["entries"][0]["timezone"], "+02:00");
+        assert_eq!(json["entries"][0]["market_open"], "08:00:00");
+        let deserialized: SearchResults = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, results);
     }
 }
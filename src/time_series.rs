@@ -1,17 +1,24 @@
 //! Time series related operations
-use chrono::DateTime;
+use crate::deserialize::Price;
+use crate::error::Error;
+use crate::request::ApiRequest;
+use chrono::{DateTime, TimeZone};
 use chrono_tz::Tz;
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use std::convert::From;
+use std::io::Read;
+use std::ops::{Bound, RangeBounds};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub(crate) enum OutputSize {
     Compact,
     Full,
 }
 
 impl OutputSize {
-    pub(crate) fn to_string(&self) -> &'static str {
+    pub(crate) fn to_string(self) -> &'static str {
         use self::OutputSize::*;
         match self {
             Compact => "compact",
@@ -20,8 +27,9 @@ impl OutputSize {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
 /// Represents the interval for an intraday time series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IntradayInterval {
     /// 1 minute.
     OneMinute,
@@ -46,23 +54,77 @@ impl IntradayInterval {
             SixtyMinutes => "60min",
         }
     }
+
+    pub(crate) fn minutes(self) -> i64 {
+        use self::IntradayInterval::*;
+        match self {
+            OneMinute => 1,
+            FiveMinutes => 5,
+            FifteenMinutes => 15,
+            ThirtyMinutes => 30,
+            SixtyMinutes => 60,
+        }
+    }
 }
 
 /// Represents a time series for a given symbol.
-#[derive(Debug, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeSeries {
     /// Symbol the time series refers to.
     pub symbol: String,
     /// Date the information was last refreshed at.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::date_time"))]
     pub last_refreshed: DateTime<Tz>,
     /// Entries in the time series, sorted by ascending dates.
     pub entries: Vec<Entry>,
 }
 
+impl TimeSeries {
+    /// Return the latest entry dated at or before `date`.
+    pub fn at_or_before<T: TimeZone>(&self, date: &DateTime<T>) -> Option<&Entry> {
+        let index = self.partition_point(date, true);
+        index.checked_sub(1).map(|index| &self.entries[index])
+    }
+
+    /// Return the entries dated within `range`, e.g. `start..end` to exclude entries
+    /// dated at `end` or `start..=end` to include them.
+    pub fn range<T: TimeZone>(&self, range: impl RangeBounds<DateTime<T>>) -> &[Entry] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.partition_point(start, false),
+            Bound::Excluded(start) => self.partition_point(start, true),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.partition_point(end, true),
+            Bound::Excluded(end) => self.partition_point(end, false),
+            Bound::Unbounded => self.entries.len(),
+        };
+        self.entries.get(start..end).unwrap_or(&[])
+    }
+
+    /// Return the latest `count` entries, or all entries if there are fewer.
+    pub fn latest(&self, count: usize) -> &[Entry] {
+        &self.entries[self.entries.len().saturating_sub(count)..]
+    }
+
+    /// Index of the first entry dated after `date`, or at or after `date` unless
+    /// `inclusive` is set.
+    fn partition_point<T: TimeZone>(&self, date: &DateTime<T>, inclusive: bool) -> usize {
+        let date = date.naive_utc();
+        self.entries.partition_point(|entry| {
+            let entry_date = entry.date.naive_utc();
+            entry_date < date || (inclusive && entry_date == date)
+        })
+    }
+}
+
 /// Represents a set of values for an equity for a given period in the time series.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     /// Date.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::date_time"))]
     pub date: DateTime<Tz>,
     /// Open value.
     pub open: f64,
@@ -74,13 +136,97 @@ pub struct Entry {
     pub close: f64,
     /// Trading volume.
     pub volume: u64,
+    /// Open, high, low and close values as exact decimals, if the entry was parsed from
+    /// prices returned by the API or loaded from a store.
+    #[cfg(feature = "decimal")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub decimals: Option<Decimals>,
 }
 
-#[derive(Debug, Clone)]
-pub(crate) enum Function {
+#[cfg(feature = "decimal")]
+impl Entry {
+    /// Open value as an exact decimal, if available.
+    pub fn open_decimal(&self) -> Option<Decimal> {
+        self.decimals.map(|decimals| decimals.open)
+    }
+
+    /// High value as an exact decimal, if available.
+    pub fn high_decimal(&self) -> Option<Decimal> {
+        self.decimals.map(|decimals| decimals.high)
+    }
+
+    /// Low value as an exact decimal, if available.
+    pub fn low_decimal(&self) -> Option<Decimal> {
+        self.decimals.map(|decimals| decimals.low)
+    }
+
+    /// Close value as an exact decimal, if available.
+    pub fn close_decimal(&self) -> Option<Decimal> {
+        self.decimals.map(|decimals| decimals.close)
+    }
+}
+
+/// Represents the values of an entry as exact decimals, as returned by the API.
+#[cfg(feature = "decimal")]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decimals {
+    /// Open value.
+    pub open: Decimal,
+    /// High value.
+    pub high: Decimal,
+    /// Low value.
+    pub low: Decimal,
+    /// Close value.
+    pub close: Decimal,
+}
+
+#[cfg(feature = "decimal")]
+impl Decimals {
+    pub(crate) fn from_prices(open: Price, high: Price, low: Price, close: Price) -> Option<Self> {
+        Some(Decimals {
+            open: open.decimal?,
+            high: high.decimal?,
+            low: low.decimal?,
+            close: close.decimal?,
+        })
+    }
+
+    /// Multiply all values by `factor`, or return `None` on overflow.
+    pub(crate) fn checked_mul(self, factor: Decimal) -> Option<Self> {
+        Some(Decimals {
+            open: self.open.checked_mul(factor)?,
+            high: self.high.checked_mul(factor)?,
+            low: self.low.checked_mul(factor)?,
+            close: self.close.checked_mul(factor)?,
+        })
+    }
+
+    /// Aggregate with the values of the following entry.
+    pub(crate) fn aggregate(self, next: Decimals) -> Self {
+        Decimals {
+            open: self.open,
+            high: self.high.max(next.high),
+            low: self.low.min(next.low),
+            close: next.close,
+        }
+    }
+}
+
+/// Represents a time series API function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Function {
+    /// Intraday time series with the given interval.
     IntraDay(IntradayInterval),
+    /// Daily time series.
     Daily,
+    /// Weekly time series.
     Weekly,
+    /// Monthly time series.
     Monthly,
 }
 
@@ -96,32 +242,69 @@ impl From<&'_ Function> for &'static str {
     }
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct TimeSeriesRequest {
+    function: Function,
+    symbol: String,
+    output_size: OutputSize,
+}
+
+impl TimeSeriesRequest {
+    pub(crate) fn new(function: Function, symbol: &str, output_size: OutputSize) -> Self {
+        TimeSeriesRequest {
+            function,
+            symbol: symbol.to_string(),
+            output_size,
+        }
+    }
+}
+
+impl ApiRequest for TimeSeriesRequest {
+    type Response = TimeSeries;
+
+    fn function(&self) -> &str {
+        (&self.function).into()
+    }
+
+    fn params(&self) -> Vec<(&str, &str)> {
+        let mut params = vec![
+            ("symbol", self.symbol.as_str()),
+            ("outputsize", self.output_size.to_string()),
+        ];
+        if let Function::IntraDay(interval) = self.function {
+            params.push(("interval", interval.to_string()));
+        }
+        params
+    }
+
+    fn parse<R: Read>(&self, reader: R) -> Result<TimeSeries, Error> {
+        parser::parse(&self.function, reader)
+    }
+}
+
 pub(crate) mod parser {
     use super::*;
-    use crate::deserialize::{from_str, parse_date};
-    use crate::error::Error;
-    use chrono_tz::Tz;
+    use crate::deserialize::{from_str, parse_date, ResponseStatus};
     use std::collections::HashMap;
-    use std::io::Read;
 
     #[derive(Debug, Deserialize)]
     struct EntryHelper {
         #[serde(rename = "1. open", deserialize_with = "from_str")]
-        pub open: f64,
+        pub open: Price,
         #[serde(rename = "2. high", deserialize_with = "from_str")]
-        pub high: f64,
+        pub high: Price,
         #[serde(rename = "3. low", deserialize_with = "from_str")]
-        pub low: f64,
+        pub low: Price,
         #[serde(rename = "4. close", deserialize_with = "from_str")]
-        pub close: f64,
+        pub close: Price,
         #[serde(rename = "5. volume", deserialize_with = "from_str")]
         pub volume: u64,
     }
 
     #[derive(Debug, Deserialize)]
     pub struct TimeSeriesHelper {
-        #[serde(rename = "Error Message")]
-        error: Option<String>,
+        #[serde(flatten)]
+        status: ResponseStatus,
         #[serde(rename = "Meta Data")]
         metadata: Option<HashMap<String, String>>,
         #[serde(flatten)]
@@ -130,10 +313,7 @@ pub(crate) mod parser {
 
     pub(crate) fn parse(function: &Function, reader: impl Read) -> Result<TimeSeries, Error> {
         let helper: TimeSeriesHelper = serde_json::from_reader(reader)?;
-
-        if let Some(error) = helper.error {
-            return Err(Error::APIError(error));
-        }
+        helper.status.check()?;
 
         let metadata = helper
             .metadata
@@ -182,11 +362,13 @@ pub(crate) mod parser {
             let date = parse_date(d, time_zone)?;
             let entry = Entry {
                 date,
-                open: v.open,
-                high: v.high,
-                low: v.low,
-                close: v.close,
+                open: v.open.value,
+                high: v.high.value,
+                low: v.low.value,
+                close: v.close.value,
                 volume: v.volume,
+                #[cfg(feature = "decimal")]
+                decimals: Decimals::from_prices(v.open, v.high, v.low, v.close),
             };
             entries.push(entry);
         }
@@ -209,6 +391,35 @@ mod tests {
     use chrono_tz::US::Eastern;
     use std::io::BufReader;
 
+    #[cfg(feature = "decimal")]
+    fn decimals(open: &str, high: &str, low: &str, close: &str) -> Option<Decimals> {
+        let decimal = |value: &str| value.parse().unwrap();
+        Some(Decimals {
+            open: decimal(open),
+            high: decimal(high),
+            low: decimal(low),
+            close: decimal(close),
+        })
+    }
+
+    #[test]
+    fn request_params() {
+        let request = TimeSeriesRequest::new(
+            Function::IntraDay(IntradayInterval::FiveMinutes),
+            "MSFT",
+            OutputSize::Full,
+        );
+        assert_eq!(request.function(), "TIME_SERIES_INTRADAY");
+        assert_eq!(
+            request.params(),
+            vec![
+                ("symbol", "MSFT"),
+                ("outputsize", "full"),
+                ("interval", "5min")
+            ]
+        );
+    }
+
     #[test]
     fn parse_intraday() {
         let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
@@ -227,6 +438,8 @@ mod tests {
                 low: 100.3850,
                 close: 100.4550,
                 volume: 67726,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("100.3975", "100.4558", "100.3850", "100.4550"),
             }
         );
         assert_eq!(
@@ -237,7 +450,9 @@ mod tests {
                 high: 100.8100,
                 low: 100.5900,
                 close: 100.7900,
-                volume: 4129781
+                volume: 4129781,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("100.6150", "100.8100", "100.5900", "100.7900"),
             }
         );
     }
@@ -257,6 +472,8 @@ mod tests {
                 low: 88.7500,
                 close: 90.1400,
                 volume: 24659472,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("89.0800", "90.2800", "88.7500", "90.1400"),
             }
         );
         assert_eq!(
@@ -268,6 +485,8 @@ mod tests {
                 low: 100.5400,
                 close: 101.6300,
                 volume: 22165128,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("101.0924", "101.9500", "100.5400", "101.6300"),
             }
         );
     }
@@ -287,6 +506,8 @@ mod tests {
                 low: 101.5000,
                 close: 112.2500,
                 volume: 157400000,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("113.4400", "114.2500", "101.5000", "112.2500"),
             }
         );
         assert_eq!(
@@ -298,6 +519,8 @@ mod tests {
                 low: 100.3800,
                 close: 101.6300,
                 volume: 122316267,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("101.2600", "102.6900", "100.3800", "101.6300"),
             }
         );
     }
@@ -317,6 +540,8 @@ mod tests {
                 low: 88.1200,
                 close: 89.3700,
                 volume: 667243800,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("98.5000", "110.0000", "88.1200", "89.3700"),
             }
         );
         assert_eq!(
@@ -328,7 +553,103 @@ mod tests {
                 low: 99.1700,
                 close: 101.6300,
                 volume: 150971891,
+                #[cfg(feature = "decimal")]
+                decimals: decimals("99.2798", "102.6900", "99.1700", "101.6300"),
             }
         );
     }
+
+    #[test]
+    fn parse_rate_limited() {
+        let data: &[u8] = include_bytes!("../tests/json/rate_limit_note.json");
+        let result = parser::parse(&Function::Daily, BufReader::new(data));
+        assert!(matches!(result, Err(crate::Error::RateLimited(_))));
+    }
+
+    #[test]
+    fn parse_premium_endpoint() {
+        let data: &[u8] = include_bytes!("../tests/json/premium_endpoint.json");
+        let result = parser::parse(&Function::Daily, BufReader::new(data));
+        assert!(matches!(result, Err(crate::Error::PremiumEndpoint(_))));
+    }
+
+    #[test]
+    fn lookup() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_daily.json");
+        let time_series = parser::parse(&Function::Daily, BufReader::new(data)).unwrap();
+        let date = |value| parse_date(value, Eastern).unwrap();
+
+        let saturday = date("2018-06-02");
+        assert_eq!(
+            time_series.at_or_before(&saturday).unwrap().date,
+            date("2018-06-01")
+        );
+        assert_eq!(
+            time_series
+                .at_or_before(&date("2018-06-04").with_timezone(&chrono::Utc))
+                .unwrap()
+                .date,
+            date("2018-06-04")
+        );
+        assert!(time_series
+            .at_or_before(&time_series.entries[0].date)
+            .is_some());
+        assert!(time_series.at_or_before(&date("2000-01-01")).is_none());
+
+        let week = time_series.range(date("2018-06-04")..date("2018-06-08"));
+        assert_eq!(week.len(), 4);
+        assert_eq!(week[0].date, date("2018-06-04"));
+        assert_eq!(
+            time_series
+                .range(date("2018-06-04")..=date("2018-06-08"))
+                .len(),
+            5
+        );
+        assert_eq!(time_series.range(date("2018-06-04")..).len(), 5);
+        assert_eq!(time_series.range(..saturday), &time_series.entries[..95]);
+        assert!(time_series
+            .range(date("2018-06-08")..date("2018-06-04"))
+            .is_empty());
+
+        assert_eq!(time_series.latest(3), &time_series.entries[97..]);
+        assert_eq!(time_series.latest(1000).len(), 100);
+        assert!(time_series.latest(0).is_empty());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn exact_decimals() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
+        let time_series = parser::parse(
+            &Function::IntraDay(IntradayInterval::OneMinute),
+            BufReader::new(data),
+        )
+        .expect("failed to parse entries");
+        let entry = &time_series.entries[0];
+        let decimal = |value: &str| value.parse::<rust_decimal::Decimal>().unwrap();
+        assert_eq!(entry.open_decimal(), Some(decimal("100.3975")));
+        assert_eq!(entry.high_decimal(), Some(decimal("100.4558")));
+        assert_eq!(entry.low_decimal(), Some(decimal("100.3850")));
+        assert_eq!(entry.close_decimal(), Some(decimal("100.4550")));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let data: &[u8] = include_bytes!("../tests/json/time_series_intraday_1min.json");
+        let time_series = parser::parse(
+            &Function::IntraDay(IntradayInterval::OneMinute),
+            BufReader::new(data),
+        )
+        .expect("failed to parse entries");
+        let json = serde_json::to_value(&time_series).unwrap();
+        assert_eq!(
+            json["last_refreshed"],
+            "2018-06-01T16:00:00-04:00[US/Eastern]"
+        );
+        assert_eq!(json["entries"][0]["open"], 100.3975);
+        let deserialized: TimeSeries = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, time_series);
+        assert_eq!(deserialized.last_refreshed.timezone(), Eastern);
+    }
 }
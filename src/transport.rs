@@ -0,0 +1,202 @@
+//! HTTP transports used by the clients
+//!
+//! By default clients send requests using [reqwest], but any type implementing
+//! [Transport] (or [blocking::Transport](crate::blocking::Transport) for the
+//! blocking client) can be set through the client builder. [MockTransport]
+//! serves canned responses without accessing the network, which allows testing
+//! code using the clients.
+use crate::error::Error;
+use reqwest::Url;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// Future returned by [Transport::fetch].
+pub type ResponseFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, Error>> + Send + 'a>>;
+
+/// Status and body of an HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Response body.
+    pub body: Vec<u8>,
+    /// Delay requested by the `Retry-After` header, if any.
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpResponse {
+    /// Create a response with the specified `status` and `body`.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> HttpResponse {
+        HttpResponse {
+            status,
+            body: body.into(),
+            retry_after: None,
+        }
+    }
+
+    /// Create a successful response with the specified `body`.
+    pub fn ok(body: impl Into<Vec<u8>>) -> HttpResponse {
+        HttpResponse::new(200, body)
+    }
+
+    /// Set the delay requested by the `Retry-After` header.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> HttpResponse {
+        self.retry_after = Some(retry_after);
+        self
+    }
+}
+
+/// Parse the value of a `Retry-After` header, either a number of seconds or an HTTP
+/// date.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// An asynchronous HTTP transport.
+pub trait Transport: Send + Sync {
+    /// Send a GET request for the fully built `url` (including the API key).
+    fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a>;
+}
+
+impl Transport for reqwest::Client {
+    fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a> {
+        Box::pin(async move {
+            let response = self.get(url.clone()).send().await?;
+            let status = response.status().as_u16();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            let body = response.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                body,
+                retry_after,
+            })
+        })
+    }
+}
+
+/// A transport serving canned responses keyed by function and parameters.
+///
+/// Requests without a matching response get an HTTP 404 response.
+#[derive(Debug, Default, Clone)]
+pub struct MockTransport {
+    responses: HashMap<RequestKey, HttpResponse>,
+}
+
+/// Function and parameters (sorted, excluding the API key) identifying a request.
+pub(crate) type RequestKey = (String, Vec<(String, String)>);
+
+pub(crate) fn request_key(url: &Url) -> RequestKey {
+    let mut function = String::new();
+    let mut params = vec![];
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "function" => function = value.into_owned(),
+            "apikey" => {}
+            _ => params.push((key.into_owned(), value.into_owned())),
+        }
+    }
+    sorted_key(function, params)
+}
+
+fn sorted_key(function: String, mut params: Vec<(String, String)>) -> RequestKey {
+    params.sort();
+    (function, params)
+}
+
+impl MockTransport {
+    /// Create a transport without any responses.
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Serve `response` for calls to `function` with the specified `params`, in any order.
+    pub fn with_response(
+        mut self,
+        function: &str,
+        params: &[(&str, &str)],
+        response: HttpResponse,
+    ) -> MockTransport {
+        let params = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        self.responses
+            .insert(sorted_key(function.to_string(), params), response);
+        self
+    }
+
+    pub(crate) fn respond(&self, url: &Url) -> HttpResponse {
+        let key = request_key(url);
+        self.responses
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| HttpResponse::new(404, format!("no response for {:?}", key)))
+    }
+}
+
+impl Transport for MockTransport {
+    fn fetch<'a>(&'a self, url: &'a Url) -> ResponseFuture<'a> {
+        Box::pin(async move { Ok(self.respond(url)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, Error};
+
+    fn transport() -> MockTransport {
+        MockTransport::new().with_response(
+            "TIME_SERIES_DAILY",
+            &[("outputsize", "compact"), ("symbol", "MSFT")],
+            HttpResponse::ok(&include_bytes!("../tests/json/time_series_daily.json")[..]),
+        )
+    }
+
+    #[tokio::test]
+    async fn mock_response() {
+        let client = Client::builder("secret")
+            .transport(transport())
+            .build()
+            .unwrap();
+        let time_series = client.get_time_series_daily("MSFT").await.unwrap();
+        assert_eq!(time_series.symbol, "MSFT");
+        assert_eq!(time_series.entries.len(), 100);
+    }
+
+    #[tokio::test]
+    async fn missing_mock_response() {
+        let client = Client::builder("secret")
+            .transport(transport())
+            .build()
+            .unwrap();
+        let result = client.get_time_series_weekly("MSFT").await;
+        assert!(matches!(result, Err(Error::ServerError(404))));
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let delay = parse_retry_after(&later.to_rfc2822()).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}